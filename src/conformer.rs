@@ -0,0 +1,226 @@
+//! Cartesian geometries for molecules and OpenMM contexts
+
+use numpy::{PyArray, PyReadonlyArray2};
+use pyo3::{
+    exceptions::PyValueError, FromPyObject, IntoPy, PyAny, PyObject, PyResult,
+    Python,
+};
+
+use crate::units::{Length, BOHR_TO_ANGSTROM};
+use crate::{Error, Result};
+
+/// the Cartesian coordinates of a set of atoms, stored as an N×3 array in Å.
+///
+/// conformers are passed to and from Python as N×3 numpy arrays in Å.
+/// converting an owned conformer to numpy hands its buffer to the array
+/// without copying, while converting a borrowed conformer or extracting one
+/// from numpy copies the data once
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Conformer {
+    /// row-major coordinates in Å. the length is always a multiple of 3
+    coords: Vec<f64>,
+}
+
+impl Conformer {
+    pub fn from_angstroms(coords: Vec<[f64; 3]>) -> Self {
+        Self {
+            coords: coords.into_iter().flatten().collect(),
+        }
+    }
+
+    pub fn from_bohr(coords: Vec<[f64; 3]>) -> Self {
+        Self::from_angstroms(coords).scale(BOHR_TO_ANGSTROM)
+    }
+
+    pub fn from_nanometers(coords: Vec<[f64; 3]>) -> Self {
+        Self::from_angstroms(coords).scale(10.0)
+    }
+
+    pub fn from_positions(positions: Vec<[Length; 3]>) -> Self {
+        Self {
+            coords: positions
+                .into_iter()
+                .flat_map(|p| p.map(|x| x.as_angstroms()))
+                .collect(),
+        }
+    }
+
+    pub fn n_atoms(&self) -> usize {
+        self.coords.len() / 3
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coords.is_empty()
+    }
+
+    /// the position of the atom at index `atom`
+    ///
+    /// # Panics
+    ///
+    /// if `atom` is out of bounds
+    pub fn position(&self, atom: usize) -> [Length; 3] {
+        let i = 3 * atom;
+        [
+            Length::angstroms(self.coords[i]),
+            Length::angstroms(self.coords[i + 1]),
+            Length::angstroms(self.coords[i + 2]),
+        ]
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = [Length; 3]> + '_ {
+        self.coords
+            .array_chunks::<3>()
+            .map(|c| c.map(Length::angstroms))
+    }
+
+    pub fn to_angstroms(&self) -> Vec<[f64; 3]> {
+        self.coords.array_chunks::<3>().copied().collect()
+    }
+
+    pub fn to_bohr(&self) -> Vec<[f64; 3]> {
+        self.clone().scale(1.0 / BOHR_TO_ANGSTROM).to_angstroms()
+    }
+
+    pub fn to_nanometers(&self) -> Vec<[f64; 3]> {
+        self.clone().scale(0.1).to_angstroms()
+    }
+
+    /// return [Error::AtomCountMismatch] unless `self` has exactly `expected`
+    /// atoms
+    pub(crate) fn check_atoms(&self, expected: usize) -> Result<()> {
+        if self.n_atoms() == expected {
+            Ok(())
+        } else {
+            Err(Error::AtomCountMismatch {
+                expected,
+                got: self.n_atoms(),
+            })
+        }
+    }
+
+    fn scale(mut self, factor: f64) -> Self {
+        self.coords.iter_mut().for_each(|x| *x *= factor);
+        self
+    }
+}
+
+impl IntoPy<PyObject> for Conformer {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let n = self.n_atoms();
+        PyArray::from_vec(py, self.coords)
+            .reshape([n, 3])
+            .expect("conformer length is always a multiple of 3")
+            .into_py(py)
+    }
+}
+
+impl IntoPy<PyObject> for &Conformer {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        PyArray::from_slice(py, &self.coords)
+            .reshape([self.n_atoms(), 3])
+            .expect("conformer length is always a multiple of 3")
+            .into_py(py)
+    }
+}
+
+impl<'a> FromPyObject<'a> for Conformer {
+    fn extract(ob: &'a PyAny) -> PyResult<Self> {
+        let array: PyReadonlyArray2<f64> = ob.extract()?;
+        let array = array.as_array();
+        if array.ncols() != 3 {
+            return Err(PyValueError::new_err(format!(
+                "expected an N×3 array of coordinates, got shape {:?}",
+                array.shape()
+            )));
+        }
+        Ok(Self {
+            coords: array.iter().copied().collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn water() -> Conformer {
+        Conformer::from_angstroms(vec![
+            [0.0, 0.0, 0.117],
+            [0.0, 0.757, -0.469],
+            [0.0, -0.757, -0.469],
+        ])
+    }
+
+    #[test]
+    fn from_angstroms() {
+        let c = water();
+        assert_eq!(c.n_atoms(), 3);
+        assert!(!c.is_empty());
+        assert!(Conformer::default().is_empty());
+        assert_eq!(c.position(1)[1], Length::angstroms(0.757));
+        assert_eq!(c.positions().count(), 3);
+        assert_eq!(c.to_angstroms()[2], [0.0, -0.757, -0.469]);
+        assert_eq!(Conformer::from_positions(c.positions().collect()), c);
+    }
+
+    #[test]
+    fn conversions() {
+        let c = water();
+        let close = |a: Vec<[f64; 3]>, b: Vec<[f64; 3]>| {
+            a.iter()
+                .flatten()
+                .zip(b.iter().flatten())
+                .all(|(x, y)| (x - y).abs() < 1e-12)
+        };
+        assert!(close(
+            Conformer::from_bohr(c.to_bohr()).to_angstroms(),
+            c.to_angstroms()
+        ));
+        assert!(close(
+            Conformer::from_nanometers(c.to_nanometers()).to_angstroms(),
+            c.to_angstroms()
+        ));
+        assert_eq!(c.to_nanometers()[1][1], 0.0757);
+    }
+
+    #[test]
+    fn check_atoms() {
+        let c = water();
+        assert!(c.check_atoms(3).is_ok());
+        assert!(matches!(
+            c.check_atoms(4),
+            Err(Error::AtomCountMismatch {
+                expected: 4,
+                got: 3
+            })
+        ));
+    }
+
+    #[test]
+    #[ignore = "requires numpy"]
+    fn numpy_round_trip() {
+        Python::with_gil(|py| {
+            let c = water();
+            let array = (&c).into_py(py);
+            let got: Conformer = array.extract(py).unwrap();
+            assert_eq!(got, c);
+            let got: Conformer = c.clone().into_py(py).extract(py).unwrap();
+            assert_eq!(got, c);
+        });
+    }
+
+    #[test]
+    #[ignore = "requires numpy"]
+    fn numpy_shape() {
+        Python::with_gil(|py| {
+            let numpy = py.import("numpy").unwrap();
+            let bad = numpy.call_method1("zeros", ((3, 2),)).unwrap();
+            let err = bad.extract::<Conformer>().unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py));
+            let flat = numpy.call_method1("zeros", (9,)).unwrap();
+            assert!(flat.extract::<Conformer>().is_err());
+            let good = numpy.call_method1("zeros", ((3, 3),)).unwrap();
+            assert_eq!(good.extract::<Conformer>().unwrap().n_atoms(), 3);
+        });
+    }
+}
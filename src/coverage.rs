@@ -0,0 +1,132 @@
+//! reports of which force field parameters are exercised by a set of molecules
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::forcefield::{ForceField, ParameterType};
+use crate::molecule::Molecule;
+use crate::{Error, Result};
+
+/// how often a single parameter is used across a dataset
+#[derive(Clone, Debug, Serialize)]
+pub struct ParameterUsage {
+    pub handler: ParameterType,
+    pub id: Option<String>,
+    pub smirks: String,
+    /// the name of a virtual site parameter. virtual sites are matched by
+    /// SMIRKS and name together, since several can share a SMIRKS pattern
+    pub name: Option<String>,
+    /// the number of molecules with at least one environment matching this
+    /// parameter
+    pub molecules: usize,
+    /// the total number of matched environments (atom tuples) across every
+    /// molecule
+    pub environments: usize,
+}
+
+/// the result of [Coverage::new]
+#[derive(Debug)]
+pub struct Coverage {
+    /// one entry for every parameter in the force field, including those
+    /// never matched, in the order they appear in the force field
+    pub parameters: Vec<ParameterUsage>,
+    /// input SMILES that could not be loaded or labeled, along with the
+    /// reason. these do not contribute to the counts in `parameters`
+    pub failed: Vec<(String, Error)>,
+}
+
+impl Coverage {
+    /// label every molecule in `smiles` with `forcefield` and count the
+    /// molecules and environments matched by each parameter. SMILES may be
+    /// mapped or unmapped
+    pub fn new<S: AsRef<str>>(
+        forcefield: &ForceField,
+        smiles: &[S],
+    ) -> Result<Self> {
+        let types = forcefield.parameter_types()?;
+
+        let mut parameters = Vec::new();
+        // (handler, smirks, name) -> index into parameters
+        let mut index = HashMap::new();
+        for &typ in &types {
+            let handler = forcefield.get_parameter_handler(typ)?;
+            for p in handler.parameters()? {
+                let smirks = p.smirks()?;
+                let name = p.name()?;
+                index.insert(
+                    (typ, smirks.clone(), name.clone()),
+                    parameters.len(),
+                );
+                parameters.push(ParameterUsage {
+                    handler: typ,
+                    id: p.id()?,
+                    smirks,
+                    name,
+                    molecules: 0,
+                    environments: 0,
+                });
+            }
+        }
+
+        let mut failed = Vec::new();
+        for smi in smiles {
+            let smi = smi.as_ref();
+            let labels = match Molecule::from_smiles(smi)
+                .and_then(|mol| mol.to_topology())
+                .and_then(|top| forcefield.label_molecules(top))
+            {
+                Ok(labels) => labels,
+                Err(e) => {
+                    failed.push((smi.to_owned(), e));
+                    continue;
+                }
+            };
+            let mut seen = HashSet::new();
+            for &typ in &types {
+                for label in labels.iter(typ) {
+                    let key = (typ, label.smirks.clone(), label.name.clone());
+                    let Some(&i) = index.get(&key) else {
+                        continue;
+                    };
+                    parameters[i].environments += 1;
+                    if seen.insert(i) {
+                        parameters[i].molecules += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(Self { parameters, failed })
+    }
+
+    /// the parameters that were not matched by any molecule
+    pub fn unused(&self) -> impl Iterator<Item = &ParameterUsage> {
+        self.parameters.iter().filter(|p| p.environments == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "requires openff-toolkit"]
+    fn virtual_sites() {
+        let ff = crate::molecule::tests::with_virtual_sites();
+        let coverage =
+            Coverage::new(&ff, &["O", "CCO", "not a smiles"]).unwrap();
+        assert_eq!(coverage.failed.len(), 1);
+        let site = coverage
+            .parameters
+            .iter()
+            .find(|p| p.handler == ParameterType::VirtualSites)
+            .unwrap();
+        assert_eq!(site.name.as_deref(), Some("EP"));
+        assert_eq!(site.molecules, 1);
+        assert_eq!(site.environments, 1);
+        assert!(coverage
+            .unused()
+            .all(|p| p.handler != ParameterType::VirtualSites));
+    }
+}
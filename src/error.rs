@@ -0,0 +1,118 @@
+use std::fmt::Display;
+
+use pyo3::{PyErr, Python};
+
+use crate::openmm::Residue;
+
+/// the error type returned by every fallible call into Python
+#[derive(Debug)]
+pub enum Error {
+    /// an exception raised by the Python interpreter. `class` is the name of
+    /// the exception type (like `SmilesParsingError`), and `traceback` is the
+    /// formatted traceback, if one was available
+    Python {
+        class: String,
+        message: String,
+        traceback: Option<String>,
+    },
+
+    /// an error from the underlying XML parser while reading an OFFXML file
+    Xml(quick_xml::Error),
+
+    /// well-formed XML that does not describe a valid OFFXML force field
+    InvalidOffxml(String),
+
+    /// residues in an OpenMM topology that did not match any of the reference
+    /// molecules passed to [crate::molecule::Topology::from_openmm]
+    UnmatchedResidues(Vec<Residue>),
+
+    /// the requested OpenMM platform is unknown or not available in the
+    /// current installation. `available` lists the names of the platforms
+    /// that are
+    MissingPlatform {
+        name: String,
+        available: Vec<String>,
+    },
+
+    /// a [crate::conformer::Conformer] with the wrong number of atoms for the
+    /// molecule or system it was used with
+    AtomCountMismatch { expected: usize, got: usize },
+
+    /// a conformer index past the end of a molecule's conformers, which may
+    /// be fewer than requested after conformer generation prunes duplicates
+    MissingConformer { index: usize, n_conformers: usize },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl From<PyErr> for Error {
+    fn from(err: PyErr) -> Self {
+        Python::with_gil(|py| {
+            let class = err
+                .get_type(py)
+                .name()
+                .map(String::from)
+                .unwrap_or_else(|_| String::from("<unknown>"));
+            let message = err.value(py).to_string();
+            let traceback = err.traceback(py).and_then(|tb| tb.format().ok());
+            Self::Python {
+                class,
+                message,
+                traceback,
+            }
+        })
+    }
+}
+
+impl From<quick_xml::Error> for Error {
+    fn from(err: quick_xml::Error) -> Self {
+        Self::Xml(err)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Python {
+                class,
+                message,
+                traceback,
+            } => {
+                if let Some(tb) = traceback {
+                    write!(f, "{tb}")?;
+                }
+                write!(f, "{class}: {message}")
+            }
+            Error::Xml(e) => write!(f, "failed to parse XML: {e}"),
+            Error::InvalidOffxml(s) => write!(f, "invalid OFFXML: {s}"),
+            Error::UnmatchedResidues(residues) => {
+                write!(f, "no matching molecule for residues")?;
+                for (i, r) in residues.iter().enumerate() {
+                    let sep = if i == 0 { ":" } else { "," };
+                    write!(f, "{sep} {} {} (chain {})", r.name, r.id, r.chain)?;
+                }
+                Ok(())
+            }
+            Error::MissingPlatform { name, available } => write!(
+                f,
+                "OpenMM platform {name} is not available. available \
+                 platforms: {}",
+                available.join(", ")
+            ),
+            Error::AtomCountMismatch { expected, got } => write!(
+                f,
+                "expected coordinates for {expected} atoms, got {got}"
+            ),
+            Error::MissingConformer {
+                index,
+                n_conformers,
+            } => write!(
+                f,
+                "conformer {index} requested, but the molecule only has \
+                 {n_conformers}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
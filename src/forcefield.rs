@@ -1,87 +1,630 @@
+use std::path::Path;
+
 use pyo3::prelude::*;
 use pyo3::types::IntoPyDict;
+use serde::Serialize;
 
 use crate::molecule::{Labels, Topology};
+use crate::offxml;
 
 use crate::openmm::{self, System};
+use crate::units::{self, Energy, Length, Quantity};
+use crate::Result;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum ParameterType {
+    Constraints,
     Bonds,
     Angles,
-    Torsions,
+    ProperTorsions,
+    ImproperTorsions,
+    VdW,
+    Electrostatics,
+    LibraryCharges,
+    ToolkitAM1BCC,
+    ChargeIncrementModel,
+    VirtualSites,
 }
 
 impl ParameterType {
-    fn as_str(&self) -> &'static str {
+    /// every parameter type, in the order the handlers are usually applied
+    pub const ALL: [ParameterType; 11] = [
+        ParameterType::Constraints,
+        ParameterType::Bonds,
+        ParameterType::Angles,
+        ParameterType::ProperTorsions,
+        ParameterType::ImproperTorsions,
+        ParameterType::VdW,
+        ParameterType::Electrostatics,
+        ParameterType::LibraryCharges,
+        ParameterType::ToolkitAM1BCC,
+        ParameterType::ChargeIncrementModel,
+        ParameterType::VirtualSites,
+    ];
+
+    /// the name of the corresponding handler in openff-toolkit and in OFFXML
+    /// files
+    pub fn as_str(&self) -> &'static str {
         match self {
+            ParameterType::Constraints => "Constraints",
             ParameterType::Bonds => "Bonds",
             ParameterType::Angles => "Angles",
-            ParameterType::Torsions => "ProperTorsions",
+            ParameterType::ProperTorsions => "ProperTorsions",
+            ParameterType::ImproperTorsions => "ImproperTorsions",
+            ParameterType::VdW => "vdW",
+            ParameterType::Electrostatics => "Electrostatics",
+            ParameterType::LibraryCharges => "LibraryCharges",
+            ParameterType::ToolkitAM1BCC => "ToolkitAM1BCC",
+            ParameterType::ChargeIncrementModel => "ChargeIncrementModel",
+            ParameterType::VirtualSites => "VirtualSites",
         }
     }
+
+    /// the inverse of [ParameterType::as_str]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.as_str() == name)
+    }
+}
+
+impl Serialize for ParameterType {
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
 }
 
+/// a handle to one of the parameter handlers in a [ForceField]. the handler
+/// is shared with the force field, so changes made through it or through the
+/// [Parameter]s it returns are reflected in the force field itself
 pub struct ParameterHandler {
     inner: Py<PyAny>,
 }
 
+impl ParameterHandler {
+    /// return handles to every parameter in the handler, in order
+    pub fn parameters(&self) -> Result<Vec<Parameter>> {
+        Python::with_gil(|py| {
+            let got: Vec<Py<PyAny>> =
+                self.inner.getattr(py, "parameters")?.extract(py)?;
+            Ok(got.into_iter().map(|inner| Parameter { inner }).collect())
+        })
+    }
+
+    /// return the parameter with id `id`, if present
+    pub fn get_parameter_by_id(&self, id: &str) -> Result<Option<Parameter>> {
+        self.get_parameter("id", id)
+    }
+
+    /// return the parameter with SMIRKS pattern `smirks`, if present
+    pub fn get_parameter_by_smirks(
+        &self,
+        smirks: &str,
+    ) -> Result<Option<Parameter>> {
+        self.get_parameter("smirks", smirks)
+    }
+
+    /// append a new parameter built from the attributes of `parameter` to the
+    /// end of the handler, returning a handle to the new parameter. cosmetic
+    /// attributes like `parameterize` are retained
+    pub fn add_parameter(
+        &mut self,
+        parameter: &offxml::Parameter,
+    ) -> Result<Parameter> {
+        let mut quantities = Vec::new();
+        let mut text = vec![("smirks", parameter.smirks.clone())];
+        if let Some(id) = &parameter.id {
+            text.push(("id", id.clone()));
+        }
+        for (k, v) in &parameter.attributes {
+            match v {
+                offxml::Value::Quantity(q) => {
+                    quantities.push((k.as_str(), (q.value, q.unit.as_str())))
+                }
+                offxml::Value::Text(s) => text.push((k.as_str(), s.clone())),
+            }
+        }
+        Python::with_gil(|py| {
+            let fun = PyModule::from_code(
+                py,
+                r#"def add_parameter(h, quantities, text):
+    from openff.units import Quantity
+    kwargs = dict(text)
+    for name, (value, unit) in quantities.items():
+        kwargs[name] = Quantity(value, unit)
+    p = h._INFOTYPE(**kwargs, allow_cosmetic_attributes=True)
+    h.add_parameter(parameter=p)
+    return p
+            "#,
+                "",
+                "",
+            )?
+            .getattr("add_parameter")?;
+            let inner = fun.call1((
+                &self.inner,
+                quantities.into_py_dict(py),
+                text.into_py_dict(py),
+            ))?;
+            Ok(Parameter {
+                inner: inner.into(),
+            })
+        })
+    }
+
+    /// remove `parameter` from the handler
+    pub fn remove_parameter(&mut self, parameter: Parameter) -> Result<()> {
+        Python::with_gil(|py| {
+            self.inner.getattr(py, "parameters")?.call_method1(
+                py,
+                "remove",
+                (parameter.inner,),
+            )?;
+            Ok(())
+        })
+    }
+
+    /// helper method for looking up a single parameter by one of its
+    /// attributes with `ParameterHandler.get_parameter`
+    fn get_parameter(
+        &self,
+        attr: &str,
+        value: &str,
+    ) -> Result<Option<Parameter>> {
+        Python::with_gil(|py| {
+            let query = [(attr, value)].into_py_dict(py);
+            let got: Vec<Py<PyAny>> = self
+                .inner
+                .call_method1(py, "get_parameter", (query,))?
+                .extract(py)?;
+            Ok(got.into_iter().next().map(|inner| Parameter { inner }))
+        })
+    }
+}
+
+/// a handle to a single parameter in a [ParameterHandler]
+pub struct Parameter {
+    inner: Py<PyAny>,
+}
+
+impl Parameter {
+    pub fn smirks(&self) -> Result<String> {
+        Python::with_gil(|py| {
+            Ok(self.inner.getattr(py, "smirks")?.extract(py)?)
+        })
+    }
+
+    pub fn id(&self) -> Result<Option<String>> {
+        Python::with_gil(|py| Ok(self.inner.getattr(py, "id")?.extract(py)?))
+    }
+
+    /// the name of a virtual site parameter, or `None` for parameter types
+    /// without names
+    pub fn name(&self) -> Result<Option<String>> {
+        Python::with_gil(|py| {
+            let inner = self.inner.as_ref(py);
+            if !inner.hasattr("name")? {
+                return Ok(None);
+            }
+            Ok(inner.getattr("name")?.extract()?)
+        })
+    }
+
+    /// return the magnitude of the quantity attribute `name` in units of
+    /// `unit`, like `get_quantity("length", "angstrom")` for a bond or
+    /// `get_quantity("k2", "kilocalorie / mole")` for a torsion
+    pub fn get_quantity(&self, name: &str, unit: &str) -> Result<f64> {
+        Python::with_gil(|py| {
+            Ok(self
+                .inner
+                .getattr(py, name)?
+                .call_method1(py, "m_as", (unit,))?
+                .extract(py)?)
+        })
+    }
+
+    /// return the quantity attribute `name` as a typed [Quantity], like
+    /// `get::<Length>("length")` for a bond
+    pub fn get<Q: Quantity>(&self, name: &str) -> Result<Q> {
+        Ok(Q::from_magnitude(self.get_quantity(name, Q::UNIT)?))
+    }
+
+    /// set the quantity attribute `name` to the typed [Quantity] `value`
+    pub fn set<Q: Quantity>(&mut self, name: &str, value: Q) -> Result<()> {
+        self.set_quantity(name, value.magnitude(), Q::UNIT)
+    }
+
+    /// set the quantity attribute `name` to `value` in units of `unit`
+    pub fn set_quantity(
+        &mut self,
+        name: &str,
+        value: f64,
+        unit: &str,
+    ) -> Result<()> {
+        Python::with_gil(|py| {
+            let units = PyModule::import(py, "openff.units")?;
+            let q = units.getattr("Quantity")?.call1((value, unit))?;
+            self.inner.setattr(py, name, q)?;
+            Ok(())
+        })
+    }
+
+    /// return the value of the cosmetic attribute `name`, like
+    /// `parameterize`, if it is set
+    pub fn get_attribute(&self, name: &str) -> Result<Option<String>> {
+        Python::with_gil(|py| {
+            let attribs: Vec<String> =
+                self.inner.getattr(py, "_cosmetic_attribs")?.extract(py)?;
+            if !attribs.iter().any(|a| a == name) {
+                return Ok(None);
+            }
+            Ok(Some(
+                self.inner
+                    .getattr(py, format!("_{name}").as_str())?
+                    .extract(py)?,
+            ))
+        })
+    }
+
+    /// set the cosmetic attribute `name` to `value`, adding it if it is not
+    /// already present
+    pub fn set_attribute(&mut self, name: &str, value: &str) -> Result<()> {
+        Python::with_gil(|py| {
+            let attribs: Vec<String> =
+                self.inner.getattr(py, "_cosmetic_attribs")?.extract(py)?;
+            if attribs.iter().any(|a| a == name) {
+                self.inner.setattr(py, format!("_{name}").as_str(), value)?;
+            } else {
+                self.inner.call_method1(
+                    py,
+                    "add_cosmetic_attribute",
+                    (name, value),
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    /// remove the cosmetic attribute `name`
+    pub fn delete_attribute(&mut self, name: &str) -> Result<()> {
+        Python::with_gil(|py| {
+            self.inner.call_method1(
+                py,
+                "delete_cosmetic_attribute",
+                (name,),
+            )?;
+            Ok(())
+        })
+    }
+}
+
 pub struct Interchange {
     inner: Py<PyAny>,
 }
 
 impl Interchange {
-    pub fn to_openmm(&self) -> System {
+    pub fn to_openmm(&self) -> Result<System> {
+        let inner =
+            Python::with_gil(|py| self.inner.call_method0(py, "to_openmm"))?;
+        Ok(System { inner })
+    }
+
+    /// like [Interchange::to_openmm], but with the vdW and electrostatic
+    /// interactions in separate forces and the improper torsions moved out of
+    /// the `PeriodicTorsionForce` into their own force. the forces are
+    /// assigned to groups with [System::assign_force_groups], ready for
+    /// [openmm::Context::get_energy_decomposition]
+    pub fn to_openmm_decomposed(&self) -> Result<System> {
         let inner = Python::with_gil(|py| {
-            self.inner.call_method0(py, "to_openmm").unwrap()
-        });
-        System { inner }
+            let to_openmm = PyModule::from_code(
+                py,
+                "def to_openmm(interchange, improper_name):
+    import openmm
+    system = interchange.to_openmm(combine_nonbonded_forces=False)
+    if 'ImproperTorsions' not in interchange.collections:
+        return system
+    # atom indices in the topology to particle indices in the system, which
+    # differ when virtual sites are interleaved with the atoms
+    particles = [
+        i
+        for i in range(system.getNumParticles())
+        if not system.isVirtualSite(i)
+    ]
+    # match impropers on the exact atom order interchange used for each key,
+    # written as (central atom, (i, k, l)), rather than on the set of atoms,
+    # since a proper torsion in a small ring can contain the same four atoms
+    # in another order
+    impropers = set()
+    for key in interchange['ImproperTorsions'].key_map:
+        a, b, c, d = (particles[i] for i in key.atom_indices)
+        impropers.add((b, (a, c, d)))
+    improper_force = openmm.PeriodicTorsionForce()
+    improper_force.setName(improper_name)
+    for force in system.getForces():
+        if not isinstance(force, openmm.PeriodicTorsionForce):
+            continue
+        for n in range(force.getNumTorsions()):
+            *atoms, per, phase, k = force.getTorsionParameters(n)
+            a, b, c, d = atoms
+            if (b, (a, c, d)) in impropers:
+                improper_force.addTorsion(*atoms, per, phase, k)
+                force.setTorsionParameters(n, *atoms, per, phase, 0.0)
+    system.addForce(improper_force)
+    return system
+",
+                "",
+                "",
+            )?
+            .getattr("to_openmm")?;
+            Ok::<_, PyErr>(
+                to_openmm
+                    .call1((&self.inner, openmm::IMPROPER_FORCE_NAME))?
+                    .into(),
+            )
+        })?;
+        let mut system = System { inner };
+        system.assign_force_groups()?;
+        Ok(system)
     }
 
-    pub fn virtual_sites(&self) -> Vec<()> {
-        todo!();
+    /// write GROMACS `{prefix}.gro` and `{prefix}.top` files
+    pub fn to_gromacs(&self, prefix: impl AsRef<Path>) -> Result<()> {
+        Python::with_gil(|py| {
+            self.inner
+                .call_method1(py, "to_gromacs", (prefix.as_ref(),))?;
+            Ok(())
+        })
     }
 
-    pub fn to_openmm_topology(&self) -> openmm::Topology {
-        todo!()
+    /// write AMBER `prmtop` and `inpcrd` files
+    pub fn to_amber(
+        &self,
+        prmtop: impl AsRef<Path>,
+        inpcrd: impl AsRef<Path>,
+    ) -> Result<()> {
+        Python::with_gil(|py| {
+            self.inner
+                .call_method1(py, "to_prmtop", (prmtop.as_ref(),))?;
+            self.inner
+                .call_method1(py, "to_inpcrd", (inpcrd.as_ref(),))?;
+            Ok(())
+        })
+    }
+
+    /// write a LAMMPS data file to `path`
+    pub fn to_lammps(&self, path: impl AsRef<Path>) -> Result<()> {
+        Python::with_gil(|py| {
+            self.inner.call_method1(py, "to_lammps", (path.as_ref(),))?;
+            Ok(())
+        })
+    }
+
+    /// return the virtual sites added to the topology by the `VirtualSites`
+    /// handler, or an empty vec if there are none
+    pub fn virtual_sites(&self) -> Result<Vec<VirtualSiteRecord>> {
+        Python::with_gil(|py| {
+            let fun = PyModule::from_code(
+                py,
+                r#"def get_virtual_sites(interchange):
+    if "VirtualSites" not in interchange.collections:
+        return []
+    collection = interchange.collections["VirtualSites"]
+
+    # place the sites with OpenMM and match them to the collection's keys
+    # by their parent atoms, in order or, failing that, in any order
+    ordered, unordered, used = {}, {}, set()
+    if interchange.positions is not None:
+        import openmm
+        from openmm import unit
+        system = interchange.to_openmm_system()
+        n = system.getNumParticles()
+        atoms = [i for i in range(n) if not system.isVirtualSite(i)]
+        atom_index = {p: i for i, p in enumerate(atoms)}
+        xyz = [openmm.Vec3(0.0, 0.0, 0.0)] * n
+        for p, x in zip(atoms, interchange.positions.m_as("nanometer")):
+            xyz[p] = openmm.Vec3(*x)
+        context = openmm.Context(
+            system,
+            openmm.VerletIntegrator(0.001),
+            openmm.Platform.getPlatformByName("Reference"),
+        )
+        context.setPositions(xyz)
+        context.computeVirtualSites()
+        xyz = context.getState(getPositions=True).getPositions(asNumpy=True)
+        xyz = xyz.value_in_unit(unit.angstrom)
+        for i in range(n):
+            if not system.isVirtualSite(i):
+                continue
+            site = system.getVirtualSite(i)
+            parents = tuple(
+                atom_index[site.getParticle(j)]
+                for j in range(site.getNumParticles())
+            )
+            ordered.setdefault(parents, []).append(i)
+            unordered.setdefault(tuple(sorted(parents)), []).append(i)
+
+    ret = []
+    for key, pot_key in collection.key_map.items():
+        params = collection.potentials[pot_key].parameters
+        def get(name, unit):
+            q = params.get(name)
+            return None if q is None else q.m_as(unit)
+        parents = tuple(key.orientation_atom_indices)
+        candidates = ordered.get(parents, []) + unordered.get(
+            tuple(sorted(parents)), []
+        )
+        position = None
+        for i in candidates:
+            if i not in used:
+                used.add(i)
+                position = xyz[i].tolist()
+                break
+        charges = params.get("charge_increments")
+        ret.append({
+            "type": key.type,
+            "name": key.name,
+            "parent_atoms": list(parents),
+            "smirks": pot_key.id,
+            "distance": get("distance", "angstrom"),
+            "out_of_plane_angle": get("outOfPlaneAngle", "degree"),
+            "in_plane_angle": get("inPlaneAngle", "degree"),
+            "charge_increment": [] if charges is None else [
+                float(q) for q in charges.m_as("elementary_charge")
+            ],
+            "sigma": get("sigma", "angstrom"),
+            "epsilon": get("epsilon", "kilocalorie / mole"),
+            "position": position,
+        })
+    return ret
+"#,
+                "",
+                "",
+            )?
+            .getattr("get_virtual_sites")?;
+            Ok(fun.call1((&self.inner,))?.extract()?)
+        })
+    }
+
+    /// return the OpenMM topology of `self`, including any virtual sites
+    pub fn to_openmm_topology(&self) -> Result<openmm::Topology> {
+        let inner = Python::with_gil(|py| {
+            self.inner.call_method0(py, "to_openmm_topology")
+        })?;
+        Ok(openmm::Topology { inner })
     }
 }
 
+/// a virtual site placed on a molecule by an [Interchange]
+#[derive(FromPyObject, Clone, Debug)]
+pub struct VirtualSiteRecord {
+    /// like `BondCharge` or `DivalentLonePair`
+    #[pyo3(item("type"))]
+    pub typ: String,
+    #[pyo3(item)]
+    pub name: String,
+    /// the indices of the atoms used to position the site, in the order of
+    /// the SMIRKS pattern that matched them
+    #[pyo3(item)]
+    pub parent_atoms: Vec<usize>,
+    /// the SMIRKS of the `VirtualSites` parameter that created this site
+    #[pyo3(item)]
+    pub smirks: String,
+    #[pyo3(item)]
+    pub distance: Option<Length>,
+    #[pyo3(item)]
+    pub out_of_plane_angle: Option<units::Angle>,
+    #[pyo3(item)]
+    pub in_plane_angle: Option<units::Angle>,
+    /// the charge moved from each parent atom to the site, in units of the
+    /// elementary charge
+    #[pyo3(item)]
+    pub charge_increment: Vec<f64>,
+    #[pyo3(item)]
+    pub sigma: Option<Length>,
+    #[pyo3(item)]
+    pub epsilon: Option<Energy>,
+    /// the Cartesian position of the site, or `None` if the interchange has
+    /// no positions
+    #[pyo3(item)]
+    pub position: Option<[Length; 3]>,
+}
+
 #[derive(Clone, Debug)]
 pub struct ForceField {
     inner: Py<PyAny>,
 }
 
 impl ForceField {
-    pub fn new(name: &str) -> anyhow::Result<Self> {
+    pub fn new(name: &str) -> Result<Self> {
+        Self::from_source(name)
+    }
+
+    /// load a force field from the contents of an OFFXML file, such as the
+    /// output of [ForceField::to_xml]
+    pub fn from_xml_str(xml: &str) -> Result<Self> {
+        Self::from_source(xml)
+    }
+
+    /// serialize `self` to OFFXML, keeping any cosmetic attributes
+    pub fn to_xml(&self) -> Result<String> {
+        Python::with_gil(|py| {
+            let kwargs =
+                [("discard_cosmetic_attributes", false)].into_py_dict(py);
+            Ok(self
+                .inner
+                .call_method(py, "to_string", ("XML",), Some(kwargs))?
+                .extract(py)?)
+        })
+    }
+
+    /// write `self` to `filename` in OFFXML format, keeping any cosmetic
+    /// attributes
+    pub fn to_file(&self, filename: impl AsRef<Path>) -> Result<()> {
+        Python::with_gil(|py| {
+            let kwargs =
+                [("discard_cosmetic_attributes", false)].into_py_dict(py);
+            self.inner.call_method(
+                py,
+                "to_file",
+                (filename.as_ref(), "XML"),
+                Some(kwargs),
+            )?;
+            Ok(())
+        })
+    }
+
+    /// helper method for calling the ForceField constructor with kwargs of
+    /// `allow_cosmetic_attributes = True`. openff-toolkit accepts either a file
+    /// name or the XML contents themselves as `source`
+    fn from_source(source: &str) -> Result<Self> {
         let inner = Python::with_gil(|py| {
             let openff_toolkit = PyModule::import(py, "openff.toolkit")?;
             let kwargs = [("allow_cosmetic_attributes", true)].into_py_dict(py);
-            Ok::<_, anyhow::Error>(
+            Ok::<_, PyErr>(
                 openff_toolkit
                     .getattr("ForceField")?
-                    .call((String::from(name),), Some(kwargs))?
+                    .call((String::from(source),), Some(kwargs))?
                     .into(),
             )
         })?;
         Ok(Self { inner })
     }
 
+    /// return the [ParameterType]s of the handlers registered in `self`.
+    /// handlers without a corresponding [ParameterType] are skipped
+    pub fn parameter_types(&self) -> Result<Vec<ParameterType>> {
+        let names: Vec<String> = Python::with_gil(|py| {
+            self.inner
+                .getattr(py, "registered_parameter_handlers")?
+                .extract(py)
+        })?;
+        Ok(names
+            .iter()
+            .filter_map(|name| ParameterType::from_name(name))
+            .collect())
+    }
+
     pub fn get_parameter_handler(
         &self,
         typ: ParameterType,
-    ) -> ParameterHandler {
+    ) -> Result<ParameterHandler> {
         let inner = Python::with_gil(|py| {
-            self.inner
-                .call_method1(py, "get_parameter_handler", (typ.as_str(),))
-                .unwrap()
-        });
-        ParameterHandler { inner }
+            self.inner.call_method1(
+                py,
+                "get_parameter_handler",
+                (typ.as_str(),),
+            )
+        })?;
+        Ok(ParameterHandler { inner })
     }
 
     pub fn create_interchange(
         &self,
         topology: Topology,
-    ) -> anyhow::Result<Interchange> {
+    ) -> Result<Interchange> {
         let inner = Python::with_gil(|py| {
             self.inner
                 .call_method1(py, "create_interchange", (topology.inner,))
@@ -89,110 +632,586 @@ impl ForceField {
         Ok(Interchange { inner })
     }
 
-    py_method! { label_molecules, Labels, top => Topology, into }
+    /// label the molecules in `top` with the parameters that would be
+    /// assigned to them by `self`
+    pub fn label_molecules(&self, top: Topology) -> Result<Labels> {
+        let inner = Python::with_gil(|py| {
+            self.inner.call_method1(py, "label_molecules", (top,))
+        })?;
+        Labels::new(inner)
+    }
 
-    pub fn bonds(&self) -> Vec<Bond> {
-        let ph = self.get_parameter_handler(ParameterType::Bonds);
-        Python::with_gil(|py| {
-            let fun = PyModule::from_code(
-                py,
-                r#"def get_bonds(h):
+    /// return the parameters in the `Bonds` handler
+    pub fn bonds(&self) -> Result<Vec<Bond>> {
+        self.extract_handler(
+            ParameterType::Bonds,
+            r#"def get_bonds(h):
     bonds = []
     for b in h:
         got = getattr(b, "_parameterize", None)
-        bonds.append({"parameterize": got, "value": b.length.magnitude, "unit": "angstrom"})
+        bonds.append({
+            "smirks": b.smirks,
+            "id": b.id,
+            "parameterize": got,
+            "length": b.length.m_as("angstrom"),
+            "k": b.k.m_as("kilocalorie / mole / angstrom ** 2"),
+        })
     return bonds
             "#,
-                "",
-                "",
-            )
-            .unwrap()
-            .getattr("get_bonds")
-            .unwrap();
-            fun.call1((ph.inner,)).unwrap().extract().unwrap()
-        })
+            "get_bonds",
+        )
     }
 
-    pub fn angles(&self) -> Vec<Angle> {
-        let ph = self.get_parameter_handler(ParameterType::Angles);
-        Python::with_gil(|py| {
-            let fun = PyModule::from_code(
-                py,
-                r#"def get_angles(h):
+    /// return the parameters in the `Angles` handler
+    pub fn angles(&self) -> Result<Vec<Angle>> {
+        self.extract_handler(
+            ParameterType::Angles,
+            r#"def get_angles(h):
     angles = []
-    #for b in h:
-    #    got = getattr(b, "_parameterize", None)
-    #    angles.append({"parameterize": got, "value": b.angle.magnitude, "unit": "angstrom"})
-    #print(angles)
+    for a in h:
+        got = getattr(a, "_parameterize", None)
+        angles.append({
+            "smirks": a.smirks,
+            "id": a.id,
+            "parameterize": got,
+            "angle": a.angle.m_as("degree"),
+            "k": a.k.m_as("kilocalorie / mole / radian ** 2"),
+        })
     return angles
             "#,
-                "",
-                "",
-            )
-            .unwrap()
-            .getattr("get_angles")
-            .unwrap();
-            fun.call1((ph.inner,)).unwrap().extract().unwrap()
+            "get_angles",
+        )
+    }
+
+    /// return the parameters in the `ProperTorsions` handler. each
+    /// [ProperTorsion] holds the full Fourier series for its SMIRKS pattern
+    pub fn proper_torsions(&self) -> Result<Vec<ProperTorsion>> {
+        self.extract_handler(
+            ParameterType::ProperTorsions,
+            TORSIONS,
+            "get_torsions",
+        )
+    }
+
+    /// return the parameters in the `ImproperTorsions` handler
+    pub fn improper_torsions(&self) -> Result<Vec<ImproperTorsion>> {
+        self.extract_handler(
+            ParameterType::ImproperTorsions,
+            TORSIONS,
+            "get_torsions",
+        )
+    }
+
+    /// return the parameters in the `vdW` handler
+    pub fn vdw(&self) -> Result<Vec<VdW>> {
+        self.extract_handler(
+            ParameterType::VdW,
+            r#"def get_vdw(h):
+    vdw = []
+    for v in h:
+        got = getattr(v, "_parameterize", None)
+        sigma = v.sigma
+        rmin_half = v.rmin_half
+        if sigma is None:
+            sigma = 2.0 * rmin_half / 2.0 ** (1.0 / 6.0)
+        if rmin_half is None:
+            rmin_half = sigma * 2.0 ** (1.0 / 6.0) / 2.0
+        vdw.append({
+            "smirks": v.smirks,
+            "id": v.id,
+            "parameterize": got,
+            "epsilon": v.epsilon.m_as("kilocalorie / mole"),
+            "sigma": sigma.m_as("angstrom"),
+            "rmin_half": rmin_half.m_as("angstrom"),
         })
+    return vdw
+            "#,
+            "get_vdw",
+        )
     }
 
-    pub fn proper_torsions(&self) -> Vec<ProperTorsion> {
-        let ph = self.get_parameter_handler(ParameterType::Torsions);
-        Python::with_gil(|py| {
-            let fun = PyModule::from_code(
-                py,
-                r#"def get_propertorsions(h):
-    propertorsions = []
-    for b in h:
-        got = getattr(b, "_parameterize", None)
-        propertorsions.append({"parameterize": got, "value": b.value.magnitude, "unit": "angstrom"})
-    print(propertorsions)
-    return propertorsions
+    /// return the settings of the `Electrostatics` handler, which has no
+    /// parameters of its own
+    pub fn electrostatics(&self) -> Result<Electrostatics> {
+        self.extract_handler(
+            ParameterType::Electrostatics,
+            r#"def get_electrostatics(h):
+    return {
+        "version": str(h.version),
+        "scale12": h.scale12,
+        "scale13": h.scale13,
+        "scale14": h.scale14,
+        "scale15": h.scale15,
+        "cutoff": h.cutoff.m_as("angstrom"),
+        "periodic_potential": str(
+            getattr(h, "periodic_potential", None) or getattr(h, "method")
+        ),
+    }
             "#,
-                "",
-                "",
-            )
-            .unwrap()
-            .getattr("get_propertorsions")
-            .unwrap();
-            fun.call1((ph.inner,)).unwrap().extract().unwrap()
+            "get_electrostatics",
+        )
+    }
+
+    /// return the parameters in the `LibraryCharges` handler, with charges in
+    /// units of the elementary charge
+    pub fn library_charges(&self) -> Result<Vec<LibraryCharge>> {
+        self.extract_handler(
+            ParameterType::LibraryCharges,
+            r#"def get_library_charges(h):
+    charges = []
+    for c in h:
+        charges.append({
+            "smirks": c.smirks,
+            "id": c.id,
+            "charge": [q.m_as("elementary_charge") for q in c.charge],
         })
+    return charges
+            "#,
+            "get_library_charges",
+        )
     }
 
-    pub fn to_xml(&self) -> String {
-        todo!();
+    /// return the settings of the `ToolkitAM1BCC` handler, which has no
+    /// parameters of its own
+    pub fn toolkit_am1bcc(&self) -> Result<ToolkitAM1BCC> {
+        self.extract_handler(
+            ParameterType::ToolkitAM1BCC,
+            r#"def get_toolkit_am1bcc(h):
+    return {"version": str(h.version)}
+            "#,
+            "get_toolkit_am1bcc",
+        )
+    }
+
+    /// return the `ChargeIncrementModel` handler's settings along with its
+    /// parameters
+    pub fn charge_increment_model(&self) -> Result<ChargeIncrementModel> {
+        self.extract_handler(
+            ParameterType::ChargeIncrementModel,
+            r#"def get_charge_increment_model(h):
+    increments = []
+    for c in h:
+        increments.append({
+            "smirks": c.smirks,
+            "id": c.id,
+            "charge_increment": [
+                q.m_as("elementary_charge") for q in c.charge_increment
+            ],
+        })
+    return {
+        "number_of_conformers": h.number_of_conformers,
+        "partial_charge_method": str(h.partial_charge_method),
+        "increments": increments,
+    }
+            "#,
+            "get_charge_increment_model",
+        )
+    }
+
+    /// return the parameters in the `VirtualSites` handler, with charges in
+    /// units of the elementary charge
+    pub fn virtual_sites(&self) -> Result<Vec<VirtualSite>> {
+        self.extract_handler(
+            ParameterType::VirtualSites,
+            r#"def get_virtual_sites(h):
+    def magnitude(q, unit):
+        return None if q is None else q.m_as(unit)
+    sites = []
+    for v in h:
+        sites.append({
+            "smirks": v.smirks,
+            "id": v.id,
+            "type": v.type,
+            "name": v.name,
+            "match": v.match,
+            "distance": magnitude(v.distance, "angstrom"),
+            "out_of_plane_angle": magnitude(
+                getattr(v, "outOfPlaneAngle", None), "degree"
+            ),
+            "in_plane_angle": magnitude(
+                getattr(v, "inPlaneAngle", None), "degree"
+            ),
+            "charge_increment": [
+                q.m_as("elementary_charge") for q in v.charge_increment
+            ],
+            "sigma": magnitude(v.sigma, "angstrom"),
+            "epsilon": magnitude(v.epsilon, "kilocalorie / mole"),
+        })
+    return sites
+            "#,
+            "get_virtual_sites",
+        )
+    }
+
+    /// return the parameters in the `Constraints` handler
+    pub fn constraints(&self) -> Result<Vec<Constraint>> {
+        self.extract_handler(
+            ParameterType::Constraints,
+            r#"def get_constraints(h):
+    constraints = []
+    for c in h:
+        d = c.distance
+        constraints.append({
+            "smirks": c.smirks,
+            "id": c.id,
+            "distance": None if d is None else d.m_as("angstrom"),
+        })
+    return constraints
+            "#,
+            "get_constraints",
+        )
+    }
+
+    /// helper method for running the Python function `fun` defined in `code`
+    /// on the parameter handler for `typ` and extracting the result
+    fn extract_handler<T>(
+        &self,
+        typ: ParameterType,
+        code: &str,
+        fun: &str,
+    ) -> Result<T>
+    where
+        T: for<'a> FromPyObject<'a>,
+    {
+        let ph = self.get_parameter_handler(typ)?;
+        Python::with_gil(|py| {
+            let fun = PyModule::from_code(py, code, "", "")?.getattr(fun)?;
+            Ok(fun.call1((ph.inner,))?.extract()?)
+        })
     }
 }
 
-#[derive(FromPyObject, Clone, Debug)]
-pub struct Unit(String);
+/// shared by [ForceField::proper_torsions] and [ForceField::improper_torsions]
+const TORSIONS: &str = r#"def get_torsions(h):
+    torsions = []
+    for t in h:
+        got = getattr(t, "_parameterize", None)
+        k = t.k or []
+        torsions.append({
+            "smirks": t.smirks,
+            "id": t.id,
+            "parameterize": got,
+            "periodicity": [int(p) for p in t.periodicity],
+            "phase": [p.m_as("degree") for p in t.phase],
+            "k": [x.m_as("kilocalorie / mole") for x in k],
+            "idivf": None if t.idivf is None else [float(i) for i in t.idivf],
+        })
+    return torsions
+"#;
 
-#[derive(FromPyObject, Debug)]
+#[derive(FromPyObject, Clone, Debug)]
 pub struct Bond {
+    #[pyo3(item)]
+    pub smirks: String,
+    #[pyo3(item)]
+    pub id: Option<String>,
     #[pyo3(item)]
     pub parameterize: Option<String>,
+    /// equilibrium bond length
     #[pyo3(item)]
-    pub value: f64,
+    pub length: Length,
     #[pyo3(item)]
-    pub unit: Unit,
+    pub k: units::ForceConstant,
 }
 
-#[derive(FromPyObject)]
+#[derive(FromPyObject, Clone, Debug)]
 pub struct Angle {
+    #[pyo3(item)]
+    pub smirks: String,
+    #[pyo3(item)]
+    pub id: Option<String>,
     #[pyo3(item)]
     pub parameterize: Option<String>,
+    /// equilibrium angle
     #[pyo3(item)]
-    pub value: f64,
+    pub angle: units::Angle,
     #[pyo3(item)]
-    pub unit: Unit,
+    pub k: units::AngleForceConstant,
 }
 
-#[derive(FromPyObject)]
-pub struct ProperTorsion {
+/// a proper or improper torsion parameter. the `periodicity`, `phase`, `k`,
+/// and `idivf` vectors hold the terms of the Fourier series in order, so `k[0]`
+/// is `k1` in the OFFXML file
+#[derive(FromPyObject, Clone, Debug)]
+pub struct Torsion {
+    #[pyo3(item)]
+    pub smirks: String,
+    #[pyo3(item)]
+    pub id: Option<String>,
     #[pyo3(item)]
     pub parameterize: Option<String>,
     #[pyo3(item)]
-    pub value: f64,
+    pub periodicity: Vec<usize>,
+    #[pyo3(item)]
+    pub phase: Vec<units::Angle>,
+    /// force constants. this is empty for proper torsions using fractional
+    /// bond order interpolation (`k_bondorder`)
+    #[pyo3(item)]
+    pub k: Vec<Energy>,
+    /// `None` if the parameter does not set `idivf` explicitly, in which case
+    /// the handler's `default_idivf` applies
     #[pyo3(item)]
-    pub unit: Unit,
+    pub idivf: Option<Vec<f64>>,
+}
+
+pub type ProperTorsion = Torsion;
+
+/// an improper torsion. the central atom is the second atom in the SMIRKS
+/// pattern
+pub type ImproperTorsion = Torsion;
+
+/// a Lennard-Jones parameter. OFFXML files can specify either `sigma` or
+/// `rmin_half`, but both are filled in here
+#[derive(FromPyObject, Clone, Debug)]
+pub struct VdW {
+    #[pyo3(item)]
+    pub smirks: String,
+    #[pyo3(item)]
+    pub id: Option<String>,
+    #[pyo3(item)]
+    pub parameterize: Option<String>,
+    /// well depth
+    #[pyo3(item)]
+    pub epsilon: Energy,
+    #[pyo3(item)]
+    pub sigma: Length,
+    #[pyo3(item)]
+    pub rmin_half: Length,
+}
+
+#[derive(FromPyObject, Clone, Debug)]
+pub struct Electrostatics {
+    #[pyo3(item)]
+    pub version: String,
+    #[pyo3(item)]
+    pub scale12: f64,
+    #[pyo3(item)]
+    pub scale13: f64,
+    #[pyo3(item)]
+    pub scale14: f64,
+    #[pyo3(item)]
+    pub scale15: f64,
+    #[pyo3(item)]
+    pub cutoff: Length,
+    /// like `Ewald3D-ConductingBoundary`
+    #[pyo3(item)]
+    pub periodic_potential: String,
+}
+
+/// a set of fixed partial charges for the atoms matched by `smirks`, in units
+/// of the elementary charge
+#[derive(FromPyObject, Clone, Debug)]
+pub struct LibraryCharge {
+    #[pyo3(item)]
+    pub smirks: String,
+    #[pyo3(item)]
+    pub id: Option<String>,
+    #[pyo3(item)]
+    pub charge: Vec<f64>,
+}
+
+#[derive(FromPyObject, Clone, Debug)]
+pub struct ToolkitAM1BCC {
+    #[pyo3(item)]
+    pub version: String,
+}
+
+#[derive(FromPyObject, Clone, Debug)]
+pub struct ChargeIncrementModel {
+    #[pyo3(item)]
+    pub number_of_conformers: usize,
+    #[pyo3(item)]
+    pub partial_charge_method: String,
+    #[pyo3(item)]
+    pub increments: Vec<ChargeIncrement>,
+}
+
+/// charge increments in units of the elementary charge
+#[derive(FromPyObject, Clone, Debug)]
+pub struct ChargeIncrement {
+    #[pyo3(item)]
+    pub smirks: String,
+    #[pyo3(item)]
+    pub id: Option<String>,
+    #[pyo3(item)]
+    pub charge_increment: Vec<f64>,
+}
+
+/// a virtual site parameter. `out_of_plane_angle` and `in_plane_angle` are
+/// only set for the site types that use them
+#[derive(FromPyObject, Clone, Debug)]
+pub struct VirtualSite {
+    #[pyo3(item)]
+    pub smirks: String,
+    #[pyo3(item)]
+    pub id: Option<String>,
+    /// like `BondCharge` or `DivalentLonePair`
+    #[pyo3(item("type"))]
+    pub typ: String,
+    #[pyo3(item)]
+    pub name: String,
+    #[pyo3(item("match"))]
+    pub matches: String,
+    #[pyo3(item)]
+    pub distance: Option<Length>,
+    #[pyo3(item)]
+    pub out_of_plane_angle: Option<units::Angle>,
+    #[pyo3(item)]
+    pub in_plane_angle: Option<units::Angle>,
+    /// in units of the elementary charge
+    #[pyo3(item)]
+    pub charge_increment: Vec<f64>,
+    #[pyo3(item)]
+    pub sigma: Option<Length>,
+    #[pyo3(item)]
+    pub epsilon: Option<Energy>,
+}
+
+/// a constraint parameter. if `distance` is `None`, the bond is constrained to
+/// its equilibrium length from the `Bonds` handler
+#[derive(FromPyObject, Clone, Debug)]
+pub struct Constraint {
+    #[pyo3(item)]
+    pub smirks: String,
+    #[pyo3(item)]
+    pub id: Option<String>,
+    #[pyo3(item)]
+    pub distance: Option<Length>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::offxml::OffXml;
+
+    #[test]
+    #[ignore = "requires openff-toolkit"]
+    fn xml_round_trip() {
+        let ff = ForceField::new("openff-2.1.0.offxml").unwrap();
+        let bonds = ff.get_parameter_handler(ParameterType::Bonds).unwrap();
+        let mut b1 = bonds.get_parameter_by_id("b1").unwrap().unwrap();
+        b1.set("length", Length::angstroms(1.234)).unwrap();
+        b1.set_attribute("parameterize", "length, k").unwrap();
+
+        let xml = ff.to_xml().unwrap();
+        let got = ForceField::from_xml_str(&xml).unwrap();
+        let got_xml = got.to_xml().unwrap();
+        assert_eq!(
+            got_xml.parse::<OffXml>().unwrap(),
+            xml.parse::<OffXml>().unwrap()
+        );
+
+        let bonds = got.get_parameter_handler(ParameterType::Bonds).unwrap();
+        let b1 = bonds.get_parameter_by_id("b1").unwrap().unwrap();
+        assert_eq!(
+            b1.get::<Length>("length").unwrap(),
+            Length::angstroms(1.234)
+        );
+        assert_eq!(
+            b1.get_attribute("parameterize").unwrap().as_deref(),
+            Some("length, k")
+        );
+    }
+
+    #[test]
+    #[ignore = "requires openff-interchange and OpenMM"]
+    fn virtual_site_records() {
+        let ff = crate::molecule::tests::with_virtual_sites();
+        let mut mol = crate::molecule::Molecule::from_smiles("O").unwrap();
+        mol.generate_conformers(1, None, crate::molecule::Toolkit::RDKit)
+            .unwrap();
+        let oxygen = mol.get_conformer(0).unwrap().position(0);
+        let interchange =
+            ff.create_interchange(mol.to_topology().unwrap()).unwrap();
+
+        let sites = interchange.virtual_sites().unwrap();
+        assert_eq!(sites.len(), 1);
+        let site = &sites[0];
+        assert_eq!(site.name, "EP");
+        assert_eq!(site.parent_atoms[0], 0);
+        assert_eq!(site.distance, Some(Length::angstroms(-0.15)));
+        assert_eq!(site.charge_increment, [0.0; 3]);
+        assert_eq!(site.sigma, Some(Length::angstroms(1.0)));
+        let position = site.position.unwrap();
+        let r = (0..3)
+            .map(|i| (position[i] - oxygen[i]).as_angstroms().powi(2))
+            .sum::<f64>()
+            .sqrt();
+        assert!((r - 0.15).abs() < 1e-6, "site is {r} Å from the oxygen");
+    }
+
+    #[test]
+    #[ignore = "requires openff-interchange and OpenMM"]
+    fn decomposed_impropers() {
+        use crate::conformer::Conformer;
+        use crate::molecule::{Molecule, Toolkit};
+        use crate::openmm::{Context, EnergyTerm, Integrator, Platform};
+        use crate::units::Time;
+
+        // cyclopropene has sp2 carbons in a three-membered ring, where
+        // proper torsions and impropers share the same sets of atoms
+        let ff = ForceField::new("openff-2.1.0.offxml").unwrap();
+        let mut mol = Molecule::from_smiles("C1=CC1").unwrap();
+        mol.generate_conformers(1, None, Toolkit::RDKit).unwrap();
+        // push the atoms out of plane so that the impropers have some energy
+        let conformer = Conformer::from_angstroms(
+            mol.get_conformer(0)
+                .unwrap()
+                .to_angstroms()
+                .into_iter()
+                .enumerate()
+                .map(|(i, [x, y, z])| [x, y, z + 0.2 * (i as f64).sin()])
+                .collect(),
+        );
+        let interchange =
+            ff.create_interchange(mol.to_topology().unwrap()).unwrap();
+        let energies = |mut system: System| {
+            system.assign_force_groups().unwrap();
+            let mut ctx = Context::new(
+                system,
+                Integrator::Verlet(Time::femtoseconds(1.0)),
+                Platform::Reference,
+            )
+            .unwrap();
+            ctx.set_positions(conformer.clone()).unwrap();
+            ctx.get_energy_decomposition().unwrap()
+        };
+
+        let decomposed = interchange.to_openmm_decomposed().unwrap();
+        let (n_moved, n_keys): (usize, usize) = Python::with_gil(|py| {
+            let fun = PyModule::from_code(
+                py,
+                "def count(system, interchange, name):
+    moved = sum(
+        f.getNumTorsions() for f in system.getForces() if f.getName() == name
+    )
+    return moved, len(interchange['ImproperTorsions'].key_map)
+",
+                "",
+                "",
+            )?
+            .getattr("count")?;
+            fun.call1((
+                &decomposed.inner,
+                &interchange.inner,
+                openmm::IMPROPER_FORCE_NAME,
+            ))?
+            .extract()
+        })
+        .unwrap();
+        assert!(n_keys > 0);
+        assert_eq!(n_moved, n_keys);
+
+        let plain = energies(interchange.to_openmm().unwrap());
+        let terms = energies(decomposed);
+        let proper = terms[&EnergyTerm::ProperTorsion];
+        let improper = terms[&EnergyTerm::ImproperTorsion];
+        assert!(improper.as_kcal_per_mol().abs() > 1e-3, "{terms:?}");
+        // without the separate force, every torsion lands in ProperTorsion
+        let torsions = plain[&EnergyTerm::ProperTorsion];
+        assert!(
+            (proper + improper - torsions).as_kcal_per_mol().abs() < 1e-6,
+            "{terms:?} do not add up to {torsions:?}"
+        );
+    }
 }
@@ -3,6 +3,14 @@
 #[macro_use]
 mod macros;
 
+pub mod conformer;
+pub mod coverage;
+mod error;
 pub mod forcefield;
 pub mod molecule;
+pub mod offxml;
 pub mod openmm;
+pub mod units;
+pub mod vibration;
+
+pub use error::{Error, Result};
@@ -1,49 +1,41 @@
 macro_rules! py_method {
 ($name:ident, $ret:ty) => {
-    pub fn $name(&self) -> $ret {
+    pub fn $name(&self) -> $crate::Result<$ret> {
         Python::with_gil(|py| {
-            self.inner
-                .call_method1(py, stringify!($name), ())
-                .unwrap()
-                .extract(py)
-                .unwrap()
+            Ok(self
+                .inner
+                .call_method1(py, stringify!($name), ())?
+                .extract(py)?)
         })
     }
 };
 
 ($name:ident, $ret:ty, $into:ident) => {
-    pub fn $name(&self) -> $ret {
+    pub fn $name(&self) -> $crate::Result<$ret> {
         let inner = Python::with_gil(|py| {
-            self.inner
-                .call_method1(py, stringify!($name), ())
-                .unwrap()
-                .into()
-        });
-        <$ret>::new(inner)
+            self.inner.call_method1(py, stringify!($name), ())
+        })?;
+        Ok(<$ret>::new(inner))
     }
 };
 
 ($name:ident, $ret:ty, $($arg:ident => $arg_typ:ty)*) => {
-    pub fn $name(&self, $($arg: $arg_typ)*) -> $ret {
+    pub fn $name(&self, $($arg: $arg_typ)*) -> $crate::Result<$ret> {
         Python::with_gil(|py| {
-            self.inner
-                .call_method1(py, stringify!($name), ($($arg,)*))
-                .unwrap()
-                .extract(py)
-                .unwrap()
+            Ok(self
+                .inner
+                .call_method1(py, stringify!($name), ($($arg,)*))?
+                .extract(py)?)
         })
     }
 };
 
 ($name:ident, $ret:ty, $($arg:ident => $arg_typ:ty)*, $into:ident) => {
-    pub fn $name(&self, $($arg: $arg_typ)*) -> $ret {
+    pub fn $name(&self, $($arg: $arg_typ)*) -> $crate::Result<$ret> {
         let inner = Python::with_gil(|py| {
-            self.inner
-                .call_method1(py, stringify!($name), ($($arg,)*))
-                .unwrap()
-                .into()
-        });
-        <$ret>::new(inner)
+            self.inner.call_method1(py, stringify!($name), ($($arg,)*))
+        })?;
+        Ok(<$ret>::new(inner))
     }
 };
 }
@@ -1,19 +1,443 @@
-//! second attempt at openff-toolkit stuff, this time wrapping Python calls with
-//! pyo3
+//! command-line interface for labeling, converting, and evaluating molecules
+//! with openff-toolkit and OpenMM. apart from `draw`, every subcommand prints
+//! a table in CSV or JSON format on stdout
+
+use std::io::Write;
+use std::path::PathBuf;
 
 use anyhow::Result;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use serde_json::Value;
 
-use ligand::forcefield::ForceField;
-use ligand::molecule::Molecule;
+use ligand::conformer::Conformer;
+use ligand::coverage::Coverage;
+use ligand::forcefield::{ForceField, ParameterType};
+use ligand::molecule::{Molecule, Toolkit};
+use ligand::openmm::{Context, EnergyTerm, Integrator, Platform};
+use ligand::units::{Force, Time};
 
-fn main() -> Result<()> {
-    let mol = Molecule::from_mapped_smiles("[Cl:2][C@:1]([F:3])([I:4])[H:5]")?;
-    dbg!(mol.to_inchi());
-    dbg!(mol.chemical_environment_matches("[#6:1]-[#9:2]"));
-    let ff = ForceField::new("openff-2.1.0.offxml")?;
-    let labels = ff.label_molecules(mol.to_topology());
-    dbg!(labels.get("Bonds"));
-    // let h = ff.get_parameter_handler(ParameterType::Bonds);
-    // dbg!(h.parameters());
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    /// the format for tabular output. in CSV output, list-valued fields are
+    /// joined with spaces
+    #[arg(long, value_enum, global = true, default_value_t = Format::Csv)]
+    format: Format,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// list the force field parameters assigned to each atom, bond, angle,
+    /// and torsion in a molecule
+    Label {
+        #[command(flatten)]
+        molecule: MoleculeArgs,
+
+        /// the force field to apply, like openff-2.1.0.offxml
+        #[arg(short, long)]
+        forcefield: String,
+    },
+
+    /// convert a molecule to another line notation
+    Convert {
+        #[command(flatten)]
+        molecule: MoleculeArgs,
+
+        #[arg(long, value_enum)]
+        to: Notation,
+    },
+
+    /// minimize a conformer with OpenMM and report the final energy in
+    /// kcal/mol and the minimized coordinates in Å
+    Minimize {
+        #[command(flatten)]
+        molecule: MoleculeArgs,
+
+        #[arg(short, long)]
+        forcefield: String,
+
+        /// the index of the conformer to minimize. if the molecule has no
+        /// conformers, as when it is read from a SMILES string, they are
+        /// generated with RDKit first
+        #[arg(short, long, default_value_t = 0)]
+        conformer: usize,
+
+        /// the RMS force tolerance for convergence, in kJ/mol/nm
+        #[arg(long, default_value_t = 10.0)]
+        tolerance: f64,
+
+        /// the maximum number of minimization steps, or 0 to run until
+        /// convergence
+        #[arg(long, default_value_t = 0)]
+        max_iterations: usize,
+    },
+
+    /// compute the single-point energy of a conformer in kcal/mol
+    Energy {
+        #[command(flatten)]
+        molecule: MoleculeArgs,
+
+        #[arg(short, long)]
+        forcefield: String,
+
+        /// the index of the conformer to evaluate. if the molecule has no
+        /// conformers, they are generated with RDKit first, as for minimize
+        #[arg(short, long, default_value_t = 0)]
+        conformer: usize,
+
+        /// report the energy of each force field term separately instead of
+        /// the total
+        #[arg(long)]
+        decompose: bool,
+    },
+
+    /// compute the RMSD in Å between the first conformers of two files
+    /// containing the same molecule, using OpenEye
+    Rmsd { reference: PathBuf, target: PathBuf },
+
+    /// compute the torsion fingerprint deviation between the first
+    /// conformers of two files containing the same molecule, using RDKit
+    Tfd { reference: PathBuf, target: PathBuf },
+
+    /// draw a molecule as an SVG image
+    Draw {
+        #[command(flatten)]
+        molecule: MoleculeArgs,
+
+        /// write the image to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// print the InChI and InChIKey of a molecule
+    Inchi {
+        #[command(flatten)]
+        molecule: MoleculeArgs,
+    },
+
+    /// report how many molecules and chemical environments match each
+    /// parameter in a force field, including parameters that are never
+    /// matched
+    Coverage {
+        #[arg(short, long)]
+        forcefield: String,
+
+        /// a file containing one SMILES string per line
+        smiles: PathBuf,
+    },
+}
+
+#[derive(Args)]
+struct MoleculeArgs {
+    /// a SMILES or InChI string, or the name of a file readable by
+    /// openff-toolkit, depending on --from
+    molecule: String,
+
+    /// how to interpret the molecule argument
+    #[arg(long, value_enum, default_value_t = Input::Smiles)]
+    from: Input,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Input {
+    Smiles,
+    MappedSmiles,
+    Inchi,
+    File,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Notation {
+    MappedSmiles,
+    Inchi,
+    Inchikey,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Csv,
+    Json,
+}
+
+impl MoleculeArgs {
+    fn load(&self) -> ligand::Result<Molecule> {
+        match self.from {
+            Input::Smiles => Molecule::from_smiles(&self.molecule),
+            Input::MappedSmiles => Molecule::from_mapped_smiles(&self.molecule),
+            Input::Inchi => Molecule::from_inchi(&self.molecule),
+            Input::File => Molecule::from_file(&self.molecule),
+        }
+    }
+}
+
+/// write `rows` to stdout in `format`. every row must serialize to a JSON
+/// object with the same keys
+fn output<T: Serialize>(rows: &[T], format: Format) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut stdout, rows)?;
+            writeln!(stdout)?;
+        }
+        Format::Csv => {
+            let mut w = csv::Writer::from_writer(stdout);
+            for (i, row) in rows.iter().enumerate() {
+                let Value::Object(map) = serde_json::to_value(row)? else {
+                    anyhow::bail!("CSV rows must be structs");
+                };
+                if i == 0 {
+                    w.write_record(map.keys())?;
+                }
+                w.write_record(map.values().map(csv_field))?;
+            }
+            w.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn csv_field(v: &Value) -> String {
+    match v {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(a) => {
+            a.iter().map(csv_field).collect::<Vec<_>>().join(" ")
+        }
+        v => v.to_string(),
+    }
+}
+
+/// build an OpenMM context for `mol` parameterized with `forcefield`. if
+/// `decompose` is true, the forces are split into groups for
+/// [Context::get_energy_decomposition]
+fn context(
+    mol: &Molecule,
+    forcefield: &str,
+    decompose: bool,
+) -> Result<Context> {
+    let ff = ForceField::new(forcefield)?;
+    let interchange = ff.create_interchange(mol.to_topology()?)?;
+    let system = if decompose {
+        interchange.to_openmm_decomposed()?
+    } else {
+        interchange.to_openmm()?
+    };
+    Ok(Context::new(
+        system,
+        Integrator::Verlet(Time::femtoseconds(1.0)),
+        Platform::Reference,
+    )?)
+}
+
+/// return the conformer of `mol` at index `idx`, generating conformers with
+/// RDKit if `mol` has none
+fn get_conformer(mol: &Molecule, idx: usize) -> Result<Conformer> {
+    let mut mol = mol.copy()?;
+    if mol.n_conformers()? == 0 {
+        mol.generate_conformers(idx + 1, None, Toolkit::RDKit)?;
+    }
+    Ok(mol.get_conformer(idx)?)
+}
+
+fn label(mol: &Molecule, forcefield: &str, format: Format) -> Result<()> {
+    #[derive(Serialize)]
+    struct Row<'a> {
+        handler: ParameterType,
+        id: Option<&'a str>,
+        smirks: &'a str,
+        atoms: &'a [usize],
+    }
+    let ff = ForceField::new(forcefield)?;
+    let labels = ff.label_molecules(mol.to_topology()?)?;
+    let mut rows = Vec::new();
+    for typ in ParameterType::ALL {
+        for l in labels.get(0, typ) {
+            rows.push(Row {
+                handler: typ,
+                id: l.id.as_deref(),
+                smirks: &l.smirks,
+                atoms: &l.atoms,
+            });
+        }
+    }
+    output(&rows, format)
+}
+
+fn convert(mol: &Molecule, to: Notation, format: Format) -> Result<()> {
+    let (key, value) = match to {
+        Notation::MappedSmiles => ("mapped_smiles", mol.to_mapped_smiles()?),
+        Notation::Inchi => ("inchi", mol.to_inchi()?),
+        Notation::Inchikey => ("inchikey", mol.to_inchikey()?),
+    };
+    let mut row = serde_json::Map::new();
+    row.insert(key.to_owned(), Value::String(value));
+    output(&[row], format)
+}
+
+fn minimize(
+    mol: &Molecule,
+    forcefield: &str,
+    conformer: usize,
+    tolerance: f64,
+    max_iterations: usize,
+    format: Format,
+) -> Result<()> {
+    #[derive(Serialize)]
+    struct Row {
+        energy: f64,
+        coordinates: Vec<f64>,
+    }
+    let mut ctx = context(mol, forcefield, false)?;
+    ctx.set_positions(get_conformer(mol, conformer)?)?;
+    ctx.minimize(Force::kj_per_mol_per_nanometer(tolerance), max_iterations)?;
+    let row = Row {
+        energy: ctx.get_energy()?.as_kcal_per_mol(),
+        coordinates: ctx.get_coordinates()?.to_angstroms().concat(),
+    };
+    output(&[row], format)
+}
+
+fn energy(
+    mol: &Molecule,
+    forcefield: &str,
+    conformer: usize,
+    decompose: bool,
+    format: Format,
+) -> Result<()> {
+    #[derive(Serialize)]
+    struct Row {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        term: Option<EnergyTerm>,
+        energy: f64,
+    }
+    let mut ctx = context(mol, forcefield, decompose)?;
+    ctx.set_positions(get_conformer(mol, conformer)?)?;
+    let rows = if decompose {
+        ctx.get_energy_decomposition()?
+            .into_iter()
+            .map(|(term, energy)| Row {
+                term: Some(term),
+                energy: energy.as_kcal_per_mol(),
+            })
+            .collect()
+    } else {
+        vec![Row {
+            term: None,
+            energy: ctx.get_energy()?.as_kcal_per_mol(),
+        }]
+    };
+    output(&rows, format)
+}
+
+fn rmsd(reference: PathBuf, target: PathBuf, format: Format) -> Result<()> {
+    #[derive(Serialize)]
+    struct Row {
+        rmsd: f64,
+    }
+    let reference = Molecule::from_file(reference)?;
+    let target = Molecule::from_file(target)?;
+    let rmsd = reference
+        .get_rmsd(&reference.get_conformer(0)?, &target.get_conformer(0)?)?;
+    output(
+        &[Row {
+            rmsd: rmsd.as_angstroms(),
+        }],
+        format,
+    )
+}
+
+fn tfd(reference: PathBuf, target: PathBuf, format: Format) -> Result<()> {
+    #[derive(Serialize)]
+    struct Row {
+        tfd: f64,
+    }
+    let reference = Molecule::from_file(reference)?;
+    let target = Molecule::from_file(target)?;
+    let tfd = reference
+        .get_tfd(&reference.get_conformer(0)?, &target.get_conformer(0)?)?;
+    output(&[Row { tfd }], format)
+}
+
+fn draw(mol: &Molecule, out: Option<PathBuf>) -> Result<()> {
+    let svg = mol.to_svg()?;
+    match out {
+        Some(path) => std::fs::write(path, svg)?,
+        None => println!("{svg}"),
+    }
     Ok(())
 }
+
+fn inchi(mol: &Molecule, format: Format) -> Result<()> {
+    #[derive(Serialize)]
+    struct Row {
+        inchi: String,
+        inchikey: String,
+    }
+    let row = Row {
+        inchi: mol.to_inchi()?,
+        inchikey: mol.to_inchikey()?,
+    };
+    output(&[row], format)
+}
+
+fn coverage(forcefield: &str, smiles: PathBuf, format: Format) -> Result<()> {
+    let ff = ForceField::new(forcefield)?;
+    let smiles = std::fs::read_to_string(smiles)?;
+    let smiles: Vec<&str> = smiles
+        .lines()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+    let coverage = Coverage::new(&ff, &smiles)?;
+    for (smi, e) in &coverage.failed {
+        eprintln!("warning: skipping {smi}: {e}");
+    }
+    output(&coverage.parameters, format)
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let format = cli.format;
+    match cli.command {
+        Command::Label {
+            molecule,
+            forcefield,
+        } => label(&molecule.load()?, &forcefield, format),
+        Command::Convert { molecule, to } => {
+            convert(&molecule.load()?, to, format)
+        }
+        Command::Minimize {
+            molecule,
+            forcefield,
+            conformer,
+            tolerance,
+            max_iterations,
+        } => minimize(
+            &molecule.load()?,
+            &forcefield,
+            conformer,
+            tolerance,
+            max_iterations,
+            format,
+        ),
+        Command::Energy {
+            molecule,
+            forcefield,
+            conformer,
+            decompose,
+        } => {
+            energy(&molecule.load()?, &forcefield, conformer, decompose, format)
+        }
+        Command::Rmsd { reference, target } => rmsd(reference, target, format),
+        Command::Tfd { reference, target } => tfd(reference, target, format),
+        Command::Draw { molecule, output } => draw(&molecule.load()?, output),
+        Command::Inchi { molecule } => inchi(&molecule.load()?, format),
+        Command::Coverage { forcefield, smiles } => {
+            coverage(&forcefield, smiles, format)
+        }
+    }
+}
@@ -1,9 +1,13 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::Path;
 
-use anyhow::Result;
 use pyo3::{prelude::*, types::IntoPyDict};
 
+use crate::conformer::Conformer;
+use crate::forcefield::ParameterType;
 use crate::openmm;
+use crate::units::Length;
+use crate::{Error, Result};
 
 #[derive(Debug, Clone)]
 pub struct Topology {
@@ -15,11 +19,77 @@ impl Topology {
         Self { inner }
     }
 
+    /// build an OpenFF topology from `topology`, using `molecules` as the
+    /// unique molecules to match against its connected components. if any
+    /// components do not match one of `molecules` by connectivity and element,
+    /// the residues containing them are returned in
+    /// [Error::UnmatchedResidues] without calling openff-toolkit. any failure
+    /// in openff-toolkit itself is returned as [Error::Python]
     pub fn from_openmm(
-        _topology: &openmm::Topology,
-        _molecules: Vec<Molecule>,
-    ) -> Self {
-        todo!();
+        topology: &openmm::Topology,
+        molecules: Vec<Molecule>,
+    ) -> Result<Self> {
+        let molecules: Vec<Py<PyAny>> =
+            molecules.into_iter().map(|m| m.inner).collect();
+        let (inner, unmatched): (Option<Py<PyAny>>, Vec<openmm::Residue>) =
+            Python::with_gil(|py| {
+                let fun = PyModule::from_code(
+                    py,
+                    r#"def from_openmm(topology, molecules):
+    from openff.toolkit import Topology
+    # check up front, since openff-toolkit reports a failed match with a bare
+    # ValueError that does not say which residues were involved. any error
+    # raised after every component has matched is openff-toolkit's own
+    unmatched = unmatched_residues(topology, molecules)
+    if unmatched:
+        return None, unmatched
+    return Topology.from_openmm(topology, unique_molecules=molecules), []
+
+def unmatched_residues(topology, molecules):
+    import networkx as nx
+
+    graph = nx.Graph()
+    for atom in topology.atoms():
+        z = 0 if atom.element is None else atom.element.atomic_number
+        graph.add_node(atom.index, atomic_number=z, residue=atom.residue)
+    for bond in topology.bonds():
+        graph.add_edge(bond[0].index, bond[1].index)
+
+    references = [m.to_networkx() for m in molecules]
+    def node_match(a, b):
+        return a["atomic_number"] == b["atomic_number"]
+
+    residues = {}
+    for component in nx.connected_components(graph):
+        sub = graph.subgraph(component)
+        if any(
+            len(ref) == len(sub)
+            and nx.is_isomorphic(sub, ref, node_match=node_match)
+            for ref in references
+        ):
+            continue
+        for i in component:
+            r = graph.nodes[i]["residue"]
+            residues[r.index] = {
+                "index": r.index,
+                "name": r.name,
+                "id": r.id,
+                "chain": r.chain.index,
+            }
+    return [residues[k] for k in sorted(residues)]
+"#,
+                    "",
+                    "",
+                )?
+                .getattr("from_openmm")?;
+                Ok::<_, Error>(
+                    fun.call1((&topology.inner, molecules))?.extract()?,
+                )
+            })?;
+        match inner {
+            Some(inner) => Ok(Self { inner }),
+            None => Err(Error::UnmatchedResidues(unmatched)),
+        }
     }
 }
 
@@ -29,43 +99,160 @@ impl IntoPy<Py<PyAny>> for Topology {
     }
 }
 
-/// A "list" of molecule labels. as I usually call it in Python, there is only
-/// one molecule, so there should only be one entry in the list. that's why
-/// `get` first indexes list[0] and then the underlying dict
-#[derive(Debug, Clone)]
+/// a single parameter assignment from [ForceField::label_molecules]: the
+/// indices of the matched atoms and the parameter applied to them
+///
+/// [ForceField::label_molecules]: crate::forcefield::ForceField::label_molecules
+#[derive(Debug, Clone, PartialEq, FromPyObject)]
+pub struct Label {
+    #[pyo3(item)]
+    pub atoms: Vec<usize>,
+    #[pyo3(item)]
+    pub id: Option<String>,
+    #[pyo3(item)]
+    pub smirks: String,
+    /// the name of a virtual site parameter, which distinguishes parameters
+    /// sharing the same SMIRKS pattern. `None` for other parameter types
+    #[pyo3(item)]
+    pub name: Option<String>,
+}
+
+/// the parameters assigned to every molecule in a [Topology], grouped by
+/// [ParameterType]. handlers not covered by [ParameterType] are skipped
+#[derive(Debug, Clone, Default)]
 pub struct Labels {
-    pub(crate) inner: Py<PyAny>,
+    molecules: Vec<HashMap<ParameterType, Vec<Label>>>,
 }
 
 impl Labels {
-    pub(crate) fn new(inner: Py<PyAny>) -> Self {
-        Self { inner }
+    pub(crate) fn new(inner: Py<PyAny>) -> Result<Self> {
+        let molecules: Vec<HashMap<String, Vec<Label>>> =
+            Python::with_gil(|py| {
+                let fun = PyModule::from_code(
+                    py,
+                    r#"def get_labels(labels):
+    ret = []
+    for mol in labels:
+        m = {}
+        for name, assignments in mol.items():
+            labels = []
+            for atoms, assigned in assignments.items():
+                # virtual site handlers assign a list of parameters to each
+                # match, since several sites can share the same parent atoms
+                if not isinstance(assigned, list):
+                    assigned = [assigned]
+                for p in assigned:
+                    if not hasattr(p, "smirks"):
+                        continue
+                    labels.append({
+                        "atoms": list(atoms),
+                        "id": getattr(p, "id", None),
+                        "smirks": p.smirks,
+                        "name": getattr(p, "name", None),
+                    })
+            m[name] = labels
+        ret.append(m)
+    return ret
+"#,
+                    "",
+                    "",
+                )?
+                .getattr("get_labels")?;
+                Ok::<_, Error>(fun.call1((inner,))?.extract()?)
+            })?;
+        let molecules = molecules
+            .into_iter()
+            .map(|m| {
+                m.into_iter()
+                    .filter_map(|(name, labels)| {
+                        Some((ParameterType::from_name(&name)?, labels))
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(Self { molecules })
     }
 
-    pub fn get(&self, index: &'static str) -> Py<PyAny> {
-        Python::with_gil(|py| {
-            self.inner
-                .call_method1(py, "__getitem__", (0,))
-                .unwrap()
-                .call_method1(py, "__getitem__", (index,))
-                .unwrap()
-        })
+    /// the number of molecules in the labeled topology
+    pub fn len(&self) -> usize {
+        self.molecules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.molecules.is_empty()
+    }
+
+    /// return the labels of type `typ` for the `molecule`th molecule, or an
+    /// empty slice if there are none
+    pub fn get(&self, molecule: usize, typ: ParameterType) -> &[Label] {
+        self.molecules
+            .get(molecule)
+            .and_then(|m| m.get(&typ))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// iterate over the labels of type `typ` across every molecule
+    pub fn iter(&self, typ: ParameterType) -> impl Iterator<Item = &Label> {
+        (0..self.len()).flat_map(move |i| self.get(i, typ))
+    }
+
+    /// the set of parameter ids of type `typ` used by the `molecule`th
+    /// molecule, like the torsions it exercises
+    pub fn ids(&self, molecule: usize, typ: ParameterType) -> BTreeSet<&str> {
+        self.get(molecule, typ)
+            .iter()
+            .filter_map(|l| l.id.as_deref())
+            .collect()
+    }
+
+    /// count the number of times each parameter id of type `typ` is used
+    /// across every molecule
+    pub fn counts(&self, typ: ParameterType) -> BTreeMap<&str, usize> {
+        let mut ret = BTreeMap::new();
+        for id in self.iter(typ).filter_map(|l| l.id.as_deref()) {
+            *ret.entry(id).or_default() += 1;
+        }
+        ret
+    }
+}
+
+/// the cheminformatics toolkit used for operations with more than one
+/// backend, like [Molecule::generate_conformers]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Toolkit {
+    RDKit,
+    OpenEye,
+}
+
+impl Toolkit {
+    /// the name of the corresponding openff-toolkit wrapper class
+    fn wrapper(&self) -> &'static str {
+        match self {
+            Toolkit::RDKit => "RDKitToolkitWrapper",
+            Toolkit::OpenEye => "OpenEyeToolkitWrapper",
+        }
     }
 }
 
+/// a handle to an openff-toolkit `Molecule`. cloning a [Molecule] only
+/// clones the reference, so both handles modify the same Python object. use
+/// [Molecule::copy] for an independent copy
 #[derive(Debug, Clone)]
 pub struct Molecule {
     pub inner: Py<PyAny>,
 }
 
-impl From<rdkit_wrapper::RWMol> for Molecule {
-    fn from(rdmol: rdkit_wrapper::RWMol) -> Self {
+impl TryFrom<rdkit_wrapper::RWMol> for Molecule {
+    type Error = Error;
+
+    fn try_from(rdmol: rdkit_wrapper::RWMol) -> Result<Self> {
         let inner = Python::with_gil(|py| {
-            let tk = PyModule::import(py, "openff.toolkit").unwrap();
-            let fun = tk.getattr("Molecule").unwrap();
-            fun.call1((rdmol,)).unwrap().into()
-        });
-        Self { inner }
+            let tk = PyModule::import(py, "openff.toolkit")?;
+            let fun = tk.getattr("Molecule")?;
+            Ok::<_, PyErr>(fun.call1((rdmol,))?.into())
+        })?;
+        Ok(Self { inner })
     }
 }
 
@@ -74,6 +261,12 @@ impl Molecule {
         Self::from_pattern("from_mapped_smiles", smiles)
     }
 
+    /// like [Molecule::from_mapped_smiles], but `smiles` does not need to
+    /// include atom map numbers
+    pub fn from_smiles(smiles: &str) -> Result<Self> {
+        Self::from_pattern("from_smiles", smiles)
+    }
+
     pub fn from_inchi(inchi: &str) -> Result<Self> {
         Self::from_pattern("from_inchi", inchi)
     }
@@ -81,14 +274,93 @@ impl Molecule {
     /// this is a bit of a stretch to use from_pattern here, but `from_file`
     /// does indeed take allow_undefined_stereo as a kwarg
     pub fn from_file(filename: impl AsRef<Path>) -> Result<Self> {
-        let filename = filename.as_ref().to_str().unwrap();
-        Self::from_pattern("from_file", filename)
+        Self::from_pattern("from_file", filename.as_ref())
+    }
+
+    pub fn n_atoms(&self) -> Result<usize> {
+        Python::with_gil(|py| {
+            Ok(self.inner.getattr(py, "n_atoms")?.extract(py)?)
+        })
+    }
+
+    pub fn n_conformers(&self) -> Result<usize> {
+        Python::with_gil(|py| {
+            Ok(self.inner.getattr(py, "n_conformers")?.extract(py)?)
+        })
+    }
+
+    /// replace any existing conformers of `self` with up to `n` new ones
+    /// generated by `toolkit`, using ETKDG for RDKit and Omega for OpenEye.
+    /// conformers closer than `rms_cutoff` to one another are pruned, or
+    /// the toolkit's default of 1 Å is used if `rms_cutoff` is `None`
+    pub fn generate_conformers(
+        &mut self,
+        n: usize,
+        rms_cutoff: Option<Length>,
+        toolkit: Toolkit,
+    ) -> Result<()> {
+        Python::with_gil(|py| {
+            let fun = PyModule::from_code(
+                py,
+                "def generate_conformers(mol, n, rms_cutoff, wrapper):
+    from openff import toolkit
+    from openff.units import Quantity
+    if rms_cutoff is not None:
+        rms_cutoff = Quantity(rms_cutoff, 'angstrom')
+    mol.generate_conformers(
+        n_conformers=n,
+        rms_cutoff=rms_cutoff,
+        toolkit_registry=getattr(toolkit, wrapper)(),
+    )
+",
+                "",
+                "",
+            )?
+            .getattr("generate_conformers")?;
+            fun.call1((
+                &self.inner,
+                n,
+                rms_cutoff.map(|r| r.as_angstroms()),
+                toolkit.wrapper(),
+            ))?;
+            Ok(())
+        })
+    }
+
+    /// return a deep copy of `self`, which can be modified without affecting
+    /// the original
+    pub fn copy(&self) -> Result<Self> {
+        Python::with_gil(|py| {
+            let molecule =
+                PyModule::import(py, "openff.toolkit")?.getattr("Molecule")?;
+            Ok(Self {
+                inner: molecule.call1((&self.inner,))?.into(),
+            })
+        })
+    }
+
+    /// remove all of the conformers from `self`
+    pub fn clear_conformers(&mut self) -> Result<()> {
+        // openff-toolkit has no public method for this: generate_conformers
+        // and add_conformer only replace or append, and the conformers
+        // property is read-only. the toolkit itself resets `_conformers` to
+        // None in the same way
+        Python::with_gil(|py| {
+            self.inner.setattr(py, "_conformers", py.None())?;
+            Ok(())
+        })
     }
 
     /// compute the RMSD between two conformers of `self` using the OpenEye
-    /// toolkit. the implementation is taken from ibstore. Note that `reference`
-    /// is expected to be in units of Bohr, while target is in Ångstroms
-    pub fn get_rmsd(&self, reference: Vec<f64>, target: Vec<f64>) -> f64 {
+    /// toolkit. the implementation is taken from ibstore
+    pub fn get_rmsd(
+        &self,
+        reference: &Conformer,
+        target: &Conformer,
+    ) -> Result<Length> {
+        let n_atoms = self.n_atoms()?;
+        reference.check_atoms(n_atoms)?;
+        target.check_atoms(n_atoms)?;
         Python::with_gil(|py| {
             let fun = PyModule::from_code(
                 py,
@@ -96,18 +368,12 @@ impl Molecule {
     from openeye import oechem
     from openff.units import Quantity, unit
     from openff.toolkit import Molecule
-    import numpy as np
     from copy import deepcopy
 
     molecule = deepcopy(molecule)
-    reference = np.array(reference)
-    reference = np.reshape(reference, (-1, 3))
-
-    target = np.array(target)
-    target = np.reshape(target, (-1, 3))
 
     molecule1 = Molecule(molecule)
-    q = Quantity(reference, unit.bohr).to('angstrom')
+    q = Quantity(reference, unit.angstrom)
     molecule1.add_conformer(q)
 
     molecule2 = Molecule(molecule)
@@ -125,14 +391,9 @@ impl Molecule {
 ",
                 "",
                 "",
-            )
-            .unwrap()
-            .getattr("get_rmsd")
-            .unwrap();
-            fun.call1((&self.inner, reference, target))
-                .unwrap()
-                .extract()
-                .unwrap()
+            )?
+            .getattr("get_rmsd")?;
+            Ok(fun.call1((&self.inner, reference, target))?.extract()?)
         })
     }
 
@@ -140,15 +401,16 @@ impl Molecule {
     /// toolkit. the implementation is taken from ibstore
     pub fn get_tfd(
         &self,
-        reference: Vec<f64>,
-        target: Vec<f64>,
-    ) -> anyhow::Result<f64> {
+        reference: &Conformer,
+        target: &Conformer,
+    ) -> Result<f64> {
+        let n_atoms = self.n_atoms()?;
+        reference.check_atoms(n_atoms)?;
+        target.check_atoms(n_atoms)?;
         Python::with_gil(|py| {
             let fun = PyModule::from_code(
                 py,
                 "def get_tfd(molecule, reference, target):
-    from openff.toolkit import Molecule
-    import numpy as np
     def _rdmol(molecule, conformer):
         from copy import deepcopy
         from openff.units import Quantity, unit
@@ -161,12 +423,6 @@ impl Molecule {
 
     from rdkit.Chem import TorsionFingerprints
 
-    reference = np.array(reference)
-    reference = np.reshape(reference, (-1, 3))
-
-    target = np.array(target)
-    target = np.reshape(target, (-1, 3))
-
     return TorsionFingerprints.GetTFDBetweenMolecules(
         _rdmol(molecule, reference),
         _rdmol(molecule, target),
@@ -183,52 +439,52 @@ impl Molecule {
     /// calls the static method Molecule.are_isomorphic, which returns
     /// `(molecules_are_isomorphic, atom_map)` and returns whether or not
     /// `molecules_are_isomorphic` (the first element of the returned tuple)
-    pub fn is_isomorphic(&self, other: Self) -> bool {
+    pub fn is_isomorphic(&self, other: Self) -> Result<bool> {
         Python::with_gil(|py| {
-            let openff_toolkit =
-                PyModule::import(py, "openff.toolkit").unwrap();
-            let molecule = openff_toolkit.getattr("Molecule").unwrap();
-            molecule
-                .call_method1("are_isomorphic", (&self.inner, other.inner))
-                .unwrap()
-                .get_item(0)
-                .unwrap()
-                .extract()
-                .unwrap()
+            let openff_toolkit = PyModule::import(py, "openff.toolkit")?;
+            let molecule = openff_toolkit.getattr("Molecule")?;
+            Ok(molecule
+                .call_method1("are_isomorphic", (&self.inner, other.inner))?
+                .get_item(0)?
+                .extract()?)
         })
     }
 
-    pub fn to_mapped_smiles(&self) -> String {
+    pub fn to_mapped_smiles(&self) -> Result<String> {
         Python::with_gil(|py| {
             let kwargs = [("mapped", true)].into_py_dict(py);
-            self.inner
-                .call_method(py, "to_smiles", (), Some(kwargs))
-                .unwrap()
-                .extract(py)
-                .unwrap()
+            Ok(self
+                .inner
+                .call_method(py, "to_smiles", (), Some(kwargs))?
+                .extract(py)?)
         })
     }
 
-    /// return the Cartesian geometry of the `idx`th conformer of `self` as a
-    /// flattened vector in units of Å
-    pub fn get_conformer(&self, idx: usize) -> Vec<f64> {
+    /// return the Cartesian geometry of the `idx`th conformer of `self`, or
+    /// [Error::MissingConformer] if there are not that many conformers
+    pub fn get_conformer(&self, idx: usize) -> Result<Conformer> {
+        let n_conformers = self.n_conformers()?;
+        if idx >= n_conformers {
+            return Err(Error::MissingConformer {
+                index: idx,
+                n_conformers,
+            });
+        }
         Python::with_gil(|py| {
             let fun = PyModule::from_code(
                 py,
                 "def get_conformer(mol, idx):
-    return mol.conformers[idx].magnitude.flatten().tolist()
+    return mol.conformers[idx].m_as('angstrom')
 ",
                 "",
                 "",
-            )
-            .unwrap()
-            .getattr("get_conformer")
-            .unwrap();
-            fun.call1((&self.inner, idx)).unwrap().extract().unwrap()
+            )?
+            .getattr("get_conformer")?;
+            Ok(fun.call1((&self.inner, idx))?.extract()?)
         })
     }
 
-    pub fn to_svg(&self) -> String {
+    pub fn to_svg(&self) -> Result<String> {
         Python::with_gil(|py| {
             let fun = PyModule::from_code(
                 py,
@@ -245,45 +501,40 @@ impl Molecule {
 ",
                 "",
                 "",
-            )
-            .unwrap()
-            .getattr("draw_rdkit")
-            .unwrap();
-            fun.call1((self.inner.clone(),)).unwrap().extract().unwrap()
+            )?
+            .getattr("draw_rdkit")?;
+            Ok(fun.call1((self.inner.clone(),))?.extract()?)
         })
     }
 
-    pub fn add_conformer(&mut self, coordinates: Vec<f64>) {
+    /// add `conformer` to `self`, returning [Error::AtomCountMismatch] if it
+    /// does not have one position for each atom
+    pub fn add_conformer(&mut self, conformer: Conformer) -> Result<()> {
+        conformer.check_atoms(self.n_atoms()?)?;
         Python::with_gil(|py| {
             let fun = PyModule::from_code(
                 py,
                 "def add_conformer(mol, coordinates):
     from openff.units import Quantity, unit
-    import numpy as np
-    a = np.array(coordinates)
-    a = np.reshape(a, (-1, 3))
-    c = Quantity(a, unit.angstrom)
-    mol.add_conformer(c)
+    mol.add_conformer(Quantity(coordinates, unit.angstrom))
 ",
                 "",
                 "",
-            )
-            .unwrap()
-            .getattr("add_conformer")
-            .unwrap();
-            fun.call1((&self.inner, coordinates)).unwrap();
-        });
+            )?
+            .getattr("add_conformer")?;
+            fun.call1((&self.inner, conformer))?;
+            Ok(())
+        })
     }
 
     /// calls `self.to_inchi(fixed_hydrogens=True)`
-    pub fn to_inchi(&self) -> String {
+    pub fn to_inchi(&self) -> Result<String> {
         Python::with_gil(|py| {
             let kwargs = [("fixed_hydrogens", true)].into_py_dict(py);
-            self.inner
-                .call_method(py, "to_inchi", (), Some(kwargs))
-                .unwrap()
-                .extract(py)
-                .unwrap()
+            Ok(self
+                .inner
+                .call_method(py, "to_inchi", (), Some(kwargs))?
+                .extract(py)?)
         })
     }
 
@@ -297,8 +548,8 @@ impl Molecule {
     /// of `allow_undefined_stereo = True`
     fn from_pattern(
         method: &str,
-        pattern: &str,
-    ) -> std::result::Result<Molecule, anyhow::Error> {
+        pattern: impl IntoPy<Py<PyAny>>,
+    ) -> Result<Molecule> {
         let inner = Python::with_gil(|py| {
             PyModule::from_code(
                 py,
@@ -307,18 +558,13 @@ logging.getLogger("openff").setLevel(logging.ERROR)
     "#,
                 "",
                 "",
-            )
-            .unwrap();
+            )?;
             let openff_toolkit = PyModule::import(py, "openff.toolkit")?;
             let kwargs = [("allow_undefined_stereo", true)].into_py_dict(py);
-            Ok::<_, anyhow::Error>(
+            Ok::<_, PyErr>(
                 openff_toolkit
                     .getattr("Molecule")?
-                    .call_method(
-                        method,
-                        (String::from(pattern),),
-                        Some(kwargs),
-                    )?
+                    .call_method(method, (pattern,), Some(kwargs))?
                     .into(),
             )
         })?;
@@ -326,3 +572,106 @@ logging.getLogger("openff").setLevel(logging.ERROR)
         Ok(Self { inner })
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::forcefield::ForceField;
+
+    /// a TIP4P-like virtual site on every water oxygen
+    const TIP4P: &str = r#"    <VirtualSites version="0.3" exclusion_policy="parents">
+        <VirtualSite smirks="[#1:2]-[#8X2H2+0:1]-[#1:3]" name="EP" type="DivalentLonePair" match="once" distance="-0.15 * angstrom" outOfPlaneAngle="0.0 * degree" charge_increment1="0.0 * elementary_charge" charge_increment2="0.0 * elementary_charge" charge_increment3="0.0 * elementary_charge" sigma="1.0 * angstrom" epsilon="0.0 * kilocalorie / mole"/>
+    </VirtualSites>
+</SMIRNOFF>"#;
+
+    /// openff-2.1.0 with a [TIP4P] virtual site handler appended
+    pub(crate) fn with_virtual_sites() -> ForceField {
+        let xml = ForceField::new("openff-2.1.0.offxml")
+            .unwrap()
+            .to_xml()
+            .unwrap()
+            .replace("</SMIRNOFF>", TIP4P);
+        ForceField::from_xml_str(&xml).unwrap()
+    }
+
+    #[test]
+    #[ignore = "requires openff-toolkit"]
+    fn virtual_site_labels() {
+        let ff = with_virtual_sites();
+        let top = Molecule::from_smiles("O").unwrap().to_topology().unwrap();
+        let labels = ff.label_molecules(top).unwrap();
+        let sites = labels.get(0, ParameterType::VirtualSites);
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].smirks, "[#1:2]-[#8X2H2+0:1]-[#1:3]");
+        assert_eq!(sites[0].name.as_deref(), Some("EP"));
+        assert_eq!(labels.get(0, ParameterType::Bonds).len(), 2);
+        assert!(labels.get(0, ParameterType::Bonds)[0].name.is_none());
+    }
+
+    /// a single water molecule, with explicit bonds
+    const WATER_PDB: &str = "\
+HETATM    1  O   HOH A   1       0.000   0.000   0.117  1.00  0.00           O
+HETATM    2  H1  HOH A   1       0.000   0.757  -0.469  1.00  0.00           H
+HETATM    3  H2  HOH A   1       0.000  -0.757  -0.469  1.00  0.00           H
+CONECT    1    2    3
+CONECT    2    1
+CONECT    3    1
+END
+";
+
+    #[test]
+    #[ignore = "requires openff-toolkit and OpenMM"]
+    fn unmatched_residues() {
+        let path = std::env::temp_dir().join("ligand-unmatched-water.pdb");
+        std::fs::write(&path, WATER_PDB).unwrap();
+        let pdb = openmm::PDBFile::new(&path).unwrap();
+        let top = pdb.topology().unwrap();
+
+        let methane = Molecule::from_smiles("C").unwrap();
+        match Topology::from_openmm(&top, vec![methane]) {
+            Err(Error::UnmatchedResidues(residues)) => {
+                assert_eq!(residues.len(), 1);
+                assert_eq!(residues[0].name, "HOH");
+                assert_eq!(residues[0].id, "1");
+            }
+            other => panic!("expected UnmatchedResidues, got {other:?}"),
+        }
+
+        let water = Molecule::from_smiles("O").unwrap();
+        assert!(Topology::from_openmm(&top, vec![water]).is_ok());
+    }
+
+    #[test]
+    #[ignore = "requires openff-toolkit"]
+    fn conformers() {
+        let mut mol = Molecule::from_smiles("CCO").unwrap();
+        assert!(matches!(
+            mol.get_conformer(0),
+            Err(Error::MissingConformer {
+                index: 0,
+                n_conformers: 0
+            })
+        ));
+
+        let mut copy = mol.copy().unwrap();
+        copy.generate_conformers(1, None, Toolkit::RDKit).unwrap();
+        assert_eq!(copy.n_conformers().unwrap(), 1);
+        assert_eq!(mol.n_conformers().unwrap(), 0);
+
+        let conformer = copy.get_conformer(0).unwrap();
+        assert_eq!(conformer.n_atoms(), 9);
+        mol.add_conformer(conformer.clone()).unwrap();
+        assert_eq!(mol.get_conformer(0).unwrap(), conformer);
+        assert!(matches!(
+            mol.add_conformer(Conformer::default()),
+            Err(Error::AtomCountMismatch {
+                expected: 9,
+                got: 0
+            })
+        ));
+
+        mol.clear_conformers().unwrap();
+        assert_eq!(mol.n_conformers().unwrap(), 0);
+        assert_eq!(copy.n_conformers().unwrap(), 1);
+    }
+}
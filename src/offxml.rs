@@ -0,0 +1,758 @@
+//! a pure-Rust representation of OFFXML force fields, for inspecting and
+//! diffing force fields without going through the Python interpreter
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::forcefield::ForceField;
+use crate::{Error, Result};
+
+/// the contents of an OFFXML file. the root `<SMIRNOFF>` element's attributes
+/// and the `<Author>` and `<Date>` children get their own fields, while every
+/// other child element is a [Handler].
+///
+/// parsing is strict: input that cannot be written back unchanged, like XML
+/// comments or elements nested inside parameters, is rejected with
+/// [Error::InvalidOffxml] instead of being dropped
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OffXml {
+    pub version: String,
+    pub aromaticity_model: Option<String>,
+    /// any other attributes on the root element, in the order they appeared
+    pub attributes: Vec<(String, String)>,
+    pub author: Option<String>,
+    pub date: Option<String>,
+    pub handlers: Vec<Handler>,
+}
+
+/// a parameter handler section like `<Bonds>` or `<ProperTorsions>`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Handler {
+    /// the tag name of the handler, like `Bonds`
+    pub name: String,
+    /// attributes on the handler element itself, like `version` and
+    /// `potential`
+    pub attributes: Vec<(String, Value)>,
+    pub parameters: Vec<Parameter>,
+}
+
+/// a single parameter like `<Bond smirks="..." id="b1" length="..." k="..."/>`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parameter {
+    /// the tag name of the parameter, like `Bond`
+    pub tag: String,
+    pub smirks: String,
+    pub id: Option<String>,
+    /// every other attribute, in the order they appeared in the file. this
+    /// includes cosmetic attributes like `parameterize`
+    pub attributes: Vec<(String, Value)>,
+}
+
+/// an attribute value. anything of the form `<number> * <unit>` is parsed as a
+/// [Quantity], and everything else is kept as text
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Quantity(Quantity),
+    Text(String),
+}
+
+/// a magnitude with its unit expression, exactly as written in the OFFXML
+/// file, like `angstrom ** -2 * mole ** -1 * kilocalorie`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: String,
+}
+
+/// a single parameter-level difference between two [OffXml]s, as returned by
+/// [OffXml::diff]. parameters are matched by id, or by SMIRKS if they have no
+/// id
+#[derive(Debug, PartialEq)]
+pub enum Difference<'a> {
+    Added {
+        handler: &'a str,
+        parameter: &'a Parameter,
+    },
+    Removed {
+        handler: &'a str,
+        parameter: &'a Parameter,
+    },
+    Changed {
+        handler: &'a str,
+        old: &'a Parameter,
+        new: &'a Parameter,
+    },
+}
+
+impl OffXml {
+    /// return the handler named `name`, if present
+    pub fn handler(&self, name: &str) -> Option<&Handler> {
+        self.handlers.iter().find(|h| h.name == name)
+    }
+
+    pub fn handler_mut(&mut self, name: &str) -> Option<&mut Handler> {
+        self.handlers.iter_mut().find(|h| h.name == name)
+    }
+
+    /// compare the parameters in `self` to those in `other`. parameters only
+    /// in `other` are [Difference::Added], and those only in `self` are
+    /// [Difference::Removed]
+    pub fn diff<'a>(&'a self, other: &'a Self) -> Vec<Difference<'a>> {
+        let mut ret = Vec::new();
+        for old in &self.handlers {
+            let handler = old.name.as_str();
+            let Some(new) = other.handler(handler) else {
+                ret.extend(old.parameters.iter().map(|parameter| {
+                    Difference::Removed { handler, parameter }
+                }));
+                continue;
+            };
+            let news: HashMap<_, _> =
+                new.parameters.iter().map(|p| (p.key(), p)).collect();
+            for p in &old.parameters {
+                match news.get(p.key()) {
+                    Some(&q) if !p.same_as(q) => {
+                        ret.push(Difference::Changed {
+                            handler,
+                            old: p,
+                            new: q,
+                        })
+                    }
+                    Some(_) => {}
+                    None => ret.push(Difference::Removed {
+                        handler,
+                        parameter: p,
+                    }),
+                }
+            }
+            let olds: HashMap<_, _> =
+                old.parameters.iter().map(|p| (p.key(), p)).collect();
+            ret.extend(
+                new.parameters
+                    .iter()
+                    .filter(|p| !olds.contains_key(p.key()))
+                    .map(|parameter| Difference::Added { handler, parameter }),
+            );
+        }
+        for new in &other.handlers {
+            if self.handler(&new.name).is_none() {
+                ret.extend(new.parameters.iter().map(|parameter| {
+                    Difference::Added {
+                        handler: &new.name,
+                        parameter,
+                    }
+                }));
+            }
+        }
+        ret
+    }
+}
+
+impl Handler {
+    /// return the parameter with id `id`, if present
+    pub fn parameter(&self, id: &str) -> Option<&Parameter> {
+        self.parameters.iter().find(|p| p.id.as_deref() == Some(id))
+    }
+
+    pub fn parameter_mut(&mut self, id: &str) -> Option<&mut Parameter> {
+        self.parameters
+            .iter_mut()
+            .find(|p| p.id.as_deref() == Some(id))
+    }
+
+    /// return the parameter with SMIRKS pattern `smirks`, if present
+    pub fn parameter_by_smirks(&self, smirks: &str) -> Option<&Parameter> {
+        self.parameters.iter().find(|p| p.smirks == smirks)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        get(&self.attributes, name)
+    }
+}
+
+impl Parameter {
+    /// return the value of the attribute `name`, if present
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        get(&self.attributes, name)
+    }
+
+    /// set the attribute `name` to `value`, replacing the existing value or
+    /// appending a new attribute
+    pub fn set(&mut self, name: &str, value: Value) {
+        match self.attributes.iter_mut().find(|(k, _)| k == name) {
+            Some((_, v)) => *v = value,
+            None => self.attributes.push((name.to_owned(), value)),
+        }
+    }
+
+    /// return the list of attributes in the cosmetic `parameterize`
+    /// attribute, or an empty vec if it is not present
+    pub fn parameterize(&self) -> Vec<&str> {
+        match self.get("parameterize") {
+            Some(Value::Text(s)) => s.split(',').map(str::trim).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// the key used to match parameters between force fields
+    fn key(&self) -> &str {
+        self.id.as_deref().unwrap_or(&self.smirks)
+    }
+
+    /// like `==`, but ignoring the order of `attributes`
+    fn same_as(&self, other: &Self) -> bool {
+        self.tag == other.tag
+            && self.smirks == other.smirks
+            && self.id == other.id
+            && self.attributes.len() == other.attributes.len()
+            && self
+                .attributes
+                .iter()
+                .all(|(k, v)| get(&other.attributes, k) == Some(v))
+    }
+}
+
+impl Value {
+    /// return the magnitude of a [Value::Quantity], or try to parse a
+    /// [Value::Text] as a plain number
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Quantity(q) => Some(q.value),
+            Value::Text(s) => s.parse().ok(),
+        }
+    }
+}
+
+fn get<'a>(attributes: &'a [(String, Value)], name: &str) -> Option<&'a Value> {
+    attributes.iter().find(|(k, _)| k == name).map(|(_, v)| v)
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        match Quantity::parse(s) {
+            Some(q) => Value::Quantity(q),
+            None => Value::Text(s.to_owned()),
+        }
+    }
+}
+
+impl Quantity {
+    /// parse a string like `1.5 * angstrom`, returning `None` if `s` is not a
+    /// number followed by a unit
+    fn parse(s: &str) -> Option<Self> {
+        let (value, unit) = s.split_once(" * ")?;
+        let value = value.trim().parse().ok()?;
+        Some(Self {
+            value,
+            unit: unit.trim().to_owned(),
+        })
+    }
+}
+
+impl Display for Quantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} * {}", self.value, self.unit)
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Quantity(q) => write!(f, "{q}"),
+            Value::Text(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+/// collect the attributes of `e` as unescaped `(key, value)` pairs
+fn attributes(e: &BytesStart) -> Result<Vec<(String, String)>> {
+    e.attributes()
+        .map(|a| {
+            let a = a.map_err(quick_xml::Error::from)?;
+            Ok((
+                String::from_utf8_lossy(a.key.as_ref()).into_owned(),
+                a.unescape_value()?.into_owned(),
+            ))
+        })
+        .collect()
+}
+
+fn tag_name(e: &BytesStart) -> String {
+    String::from_utf8_lossy(e.name().as_ref()).into_owned()
+}
+
+fn parse_parameter(e: &BytesStart) -> Result<Parameter> {
+    let tag = tag_name(e);
+    let mut smirks = None;
+    let mut id = None;
+    let mut attrs = Vec::new();
+    for (k, v) in attributes(e)? {
+        match k.as_str() {
+            "smirks" => smirks = Some(v),
+            "id" => id = Some(v),
+            _ => attrs.push((k, Value::from(v.as_str()))),
+        }
+    }
+    let Some(smirks) = smirks else {
+        return Err(Error::InvalidOffxml(format!(
+            "{tag} parameter without smirks"
+        )));
+    };
+    Ok(Parameter {
+        tag,
+        smirks,
+        id,
+        attributes: attrs,
+    })
+}
+
+impl FromStr for OffXml {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut reader = Reader::from_str(s);
+        reader.trim_text(true);
+
+        let mut ret: Option<OffXml> = None;
+        let mut handler: Option<Handler> = None;
+        // the name of the text-only element being read, like `Author`
+        let mut text_tag: Option<String> = None;
+        let mut depth = 0;
+        let invalid = |msg: &str| Err(Error::InvalidOffxml(msg.to_owned()));
+        loop {
+            let (e, empty) = match reader.read_event()? {
+                Event::Start(e) => (e, false),
+                Event::Empty(e) => (e, true),
+                Event::End(_) => {
+                    depth -= 1;
+                    if depth == 1 {
+                        text_tag = None;
+                        if let (Some(ff), Some(h)) =
+                            (ret.as_mut(), handler.take())
+                        {
+                            ff.handlers.push(h);
+                        }
+                    }
+                    continue;
+                }
+                Event::Text(t) => {
+                    let (Some(ff), Some(tag), 2) =
+                        (ret.as_mut(), &text_tag, depth)
+                    else {
+                        return invalid("text outside of Author and Date");
+                    };
+                    let text = Some(t.unescape()?.into_owned());
+                    match tag.as_str() {
+                        "Author" => ff.author = text,
+                        _ => ff.date = text,
+                    }
+                    continue;
+                }
+                Event::Eof if depth != 0 => {
+                    return invalid("unexpected end of file");
+                }
+                Event::Eof => break,
+                Event::Comment(_) => {
+                    return invalid("comments are not supported");
+                }
+                Event::CData(_) | Event::PI(_) | Event::DocType(_) => {
+                    return invalid("unsupported XML construct");
+                }
+                Event::Decl(_) => continue,
+            };
+
+            match depth {
+                0 => {
+                    if ret.is_some() {
+                        return invalid("multiple root elements");
+                    }
+                    let name = tag_name(&e);
+                    if name != "SMIRNOFF" {
+                        return Err(Error::InvalidOffxml(format!(
+                            "expected SMIRNOFF root element, found {name}"
+                        )));
+                    }
+                    let mut ff = OffXml::default();
+                    for (k, v) in attributes(&e)? {
+                        match k.as_str() {
+                            "version" => ff.version = v,
+                            "aromaticity_model" => {
+                                ff.aromaticity_model = Some(v)
+                            }
+                            _ => ff.attributes.push((k, v)),
+                        }
+                    }
+                    ret = Some(ff);
+                }
+                1 => {
+                    let name = tag_name(&e);
+                    if name == "Author" || name == "Date" {
+                        if let Some(ff) = ret.as_mut() {
+                            let text = Some(String::new());
+                            match name.as_str() {
+                                "Author" => ff.author = text,
+                                _ => ff.date = text,
+                            }
+                        }
+                        text_tag = Some(name);
+                    } else {
+                        let h = Handler {
+                            name,
+                            attributes: attributes(&e)?
+                                .into_iter()
+                                .map(|(k, v)| (k, Value::from(v.as_str())))
+                                .collect(),
+                            parameters: Vec::new(),
+                        };
+                        match (empty, ret.as_mut()) {
+                            (true, Some(ff)) => ff.handlers.push(h),
+                            _ => handler = Some(h),
+                        }
+                    }
+                }
+                2 => {
+                    let Some(h) = handler.as_mut() else {
+                        return Err(Error::InvalidOffxml(format!(
+                            "unexpected {} element inside {}",
+                            tag_name(&e),
+                            text_tag.as_deref().unwrap_or("SMIRNOFF"),
+                        )));
+                    };
+                    h.parameters.push(parse_parameter(&e)?);
+                }
+                _ => {
+                    return Err(Error::InvalidOffxml(format!(
+                        "unexpected {} element inside a parameter",
+                        tag_name(&e)
+                    )));
+                }
+            }
+
+            if !empty {
+                depth += 1;
+            }
+        }
+
+        ret.ok_or_else(|| {
+            Error::InvalidOffxml(String::from("missing SMIRNOFF element"))
+        })
+    }
+}
+
+fn write_attributes(
+    f: &mut std::fmt::Formatter<'_>,
+    attributes: &[(String, Value)],
+) -> std::fmt::Result {
+    for (k, v) in attributes {
+        write!(f, r#" {k}="{}""#, escape(&v.to_string()))?;
+    }
+    Ok(())
+}
+
+impl Display for OffXml {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+        write!(f, r#"<SMIRNOFF version="{}""#, escape(&self.version))?;
+        if let Some(model) = &self.aromaticity_model {
+            write!(f, r#" aromaticity_model="{}""#, escape(model))?;
+        }
+        for (k, v) in &self.attributes {
+            write!(f, r#" {k}="{}""#, escape(v))?;
+        }
+        writeln!(f, ">")?;
+        if let Some(author) = &self.author {
+            writeln!(f, "    <Author>{}</Author>", escape(author))?;
+        }
+        if let Some(date) = &self.date {
+            writeln!(f, "    <Date>{}</Date>", escape(date))?;
+        }
+        for h in &self.handlers {
+            write!(f, "    <{}", h.name)?;
+            write_attributes(f, &h.attributes)?;
+            if h.parameters.is_empty() {
+                writeln!(f, "/>")?;
+                continue;
+            }
+            writeln!(f, ">")?;
+            for p in &h.parameters {
+                write!(
+                    f,
+                    r#"        <{} smirks="{}""#,
+                    p.tag,
+                    escape(&p.smirks)
+                )?;
+                if let Some(id) = &p.id {
+                    write!(f, r#" id="{}""#, escape(id))?;
+                }
+                write_attributes(f, &p.attributes)?;
+                writeln!(f, "/>")?;
+            }
+            writeln!(f, "    </{}>", h.name)?;
+        }
+        writeln!(f, "</SMIRNOFF>")
+    }
+}
+
+impl TryFrom<&ForceField> for OffXml {
+    type Error = Error;
+
+    fn try_from(ff: &ForceField) -> Result<Self> {
+        ff.to_xml()?.parse()
+    }
+}
+
+impl TryFrom<&OffXml> for ForceField {
+    type Error = Error;
+
+    fn try_from(offxml: &OffXml) -> Result<Self> {
+        ForceField::from_xml_str(&offxml.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORCE_FIELD: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<SMIRNOFF version="0.3" aromaticity_model="OEAroModel_MDL" extra="kept">
+    <Author>A &amp; B &lt;ab@example.com&gt;</Author>
+    <Date>2023-05-01</Date>
+    <Constraints version="0.3">
+        <Constraint smirks="[#1:1]-[*:2]" id="c1"></Constraint>
+    </Constraints>
+    <Bonds version="0.4" potential="harmonic" fractional_bondorder_method="AM1-Wiberg">
+        <Bond smirks="[#6X4:1]-[#6X4:2]" id="b1" length="1.527 * angstrom" k="419.9 * angstrom ** -2 * mole ** -1 * kilocalorie"/>
+        <Bond smirks="[#6X4:1]-[#1:2]" id="b2" length="1.09 * angstrom" k="740.0 * angstrom ** -2 * mole ** -1 * kilocalorie" parameterize="length, k"/>
+    </Bonds>
+    <LibraryCharges version="0.3"></LibraryCharges>
+    <ToolkitAM1BCC version="0.3"/>
+</SMIRNOFF>
+"#;
+
+    fn small() -> OffXml {
+        FORCE_FIELD.parse().unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let ff = small();
+        assert_eq!(ff.version, "0.3");
+        assert_eq!(ff.aromaticity_model.as_deref(), Some("OEAroModel_MDL"));
+        assert_eq!(ff.attributes, [("extra".to_owned(), "kept".to_owned())]);
+        assert_eq!(ff.handlers.len(), 4);
+
+        let bonds = ff.handler("Bonds").unwrap();
+        assert_eq!(bonds.get("potential"), Some(&Value::from("harmonic")));
+        let b2 = bonds.parameter("b2").unwrap();
+        assert_eq!(b2.tag, "Bond");
+        assert_eq!(b2.smirks, "[#6X4:1]-[#1:2]");
+        assert_eq!(
+            b2.get("length"),
+            Some(&Value::Quantity(Quantity {
+                value: 1.09,
+                unit: "angstrom".to_owned()
+            }))
+        );
+        assert_eq!(b2.parameterize(), ["length", "k"]);
+        assert_eq!(
+            bonds.parameter_by_smirks("[#6X4:1]-[#6X4:2]").unwrap().id,
+            Some("b1".to_owned())
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let ff = small();
+        let written = ff.to_string();
+        let got: OffXml = written.parse().unwrap();
+        assert_eq!(got, ff);
+        // writing is deterministic, so a second trip is textually identical
+        assert_eq!(got.to_string(), written);
+    }
+
+    #[test]
+    fn escaped_text() {
+        let ff = small();
+        assert_eq!(ff.author.as_deref(), Some("A & B <ab@example.com>"));
+        assert_eq!(ff.date.as_deref(), Some("2023-05-01"));
+        let written = ff.to_string();
+        assert!(written.contains("<Author>A &amp; B &lt;ab@example.com&gt;"));
+
+        let empty: OffXml = r#"<SMIRNOFF version="0.3"><Author></Author>
+            <Date/></SMIRNOFF>"#
+            .parse()
+            .unwrap();
+        assert_eq!(empty.author.as_deref(), Some(""));
+        assert_eq!(empty.date.as_deref(), Some(""));
+        assert_eq!(empty.to_string().parse::<OffXml>().unwrap(), empty);
+    }
+
+    #[test]
+    fn empty_handlers() {
+        let ff = small();
+        for name in ["LibraryCharges", "ToolkitAM1BCC"] {
+            let h = ff.handler(name).unwrap();
+            assert!(h.parameters.is_empty());
+            assert_eq!(h.get("version"), Some(&Value::from("0.3")));
+        }
+        // a parameter with an explicit end tag is read like a self-closing one
+        let c1 = ff.handler("Constraints").unwrap().parameter("c1").unwrap();
+        assert!(c1.attributes.is_empty());
+        assert!(ff
+            .to_string()
+            .contains(r#"<LibraryCharges version="0.3"/>"#));
+    }
+
+    #[test]
+    fn quantity() {
+        assert_eq!(
+            Quantity::parse("1.5 * angstrom"),
+            Some(Quantity {
+                value: 1.5,
+                unit: "angstrom".to_owned()
+            })
+        );
+        let k = Quantity::parse("-2e3 * angstrom ** -2 * mole ** -1").unwrap();
+        assert_eq!(k.value, -2000.0);
+        assert_eq!(k.unit, "angstrom ** -2 * mole ** -1");
+        assert_eq!(Quantity::parse(&k.to_string()), Some(k));
+
+        assert_eq!(Quantity::parse("angstrom"), None);
+        assert_eq!(Quantity::parse("one * angstrom"), None);
+        assert_eq!(Value::from("1.5"), Value::Text("1.5".to_owned()));
+        assert_eq!(Value::from("1.5").as_f64(), Some(1.5));
+        assert_eq!(Value::from("2 * degree").as_f64(), Some(2.0));
+    }
+
+    #[test]
+    fn diff() {
+        let old = small();
+        let mut new = old.clone();
+        assert!(old.diff(&new).is_empty());
+
+        let bonds = new.handler_mut("Bonds").unwrap();
+        bonds
+            .parameter_mut("b1")
+            .unwrap()
+            .set("length", Value::from("1.6 * angstrom"));
+        bonds.parameters.retain(|p| p.id.as_deref() != Some("b2"));
+        let added = Parameter {
+            tag: "Bond".to_owned(),
+            smirks: "[#8:1]-[#1:2]".to_owned(),
+            id: Some("b3".to_owned()),
+            attributes: Vec::new(),
+        };
+        bonds.parameters.push(added.clone());
+
+        let diff = old.diff(&new);
+        let bonds = old.handler("Bonds").unwrap();
+        let new_bonds = new.handler("Bonds").unwrap();
+        assert_eq!(
+            diff,
+            [
+                Difference::Changed {
+                    handler: "Bonds",
+                    old: bonds.parameter("b1").unwrap(),
+                    new: new_bonds.parameter("b1").unwrap(),
+                },
+                Difference::Removed {
+                    handler: "Bonds",
+                    parameter: bonds.parameter("b2").unwrap(),
+                },
+                Difference::Added {
+                    handler: "Bonds",
+                    parameter: &added,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_by_smirks() {
+        let xml = |k: &str| {
+            format!(
+                r#"<SMIRNOFF version="0.3"><Bonds>
+                <Bond smirks="[#6:1]-[#6:2]" k="{k} * mole ** -1"/>
+                <Bond smirks="[#6:1]-[#1:2]" k="1.0 * mole ** -1"/>
+                </Bonds></SMIRNOFF>"#
+            )
+        };
+        let old: OffXml = xml("1.0").parse().unwrap();
+        let new: OffXml = xml("2.0").parse().unwrap();
+        let diff = old.diff(&new);
+        assert_eq!(diff.len(), 1);
+        let Difference::Changed { old: p, new: q, .. } = diff[0] else {
+            panic!("expected a change, got {diff:?}");
+        };
+        assert_eq!(p.smirks, "[#6:1]-[#6:2]");
+        assert_eq!(p.get("k").unwrap().as_f64(), Some(1.0));
+        assert_eq!(q.get("k").unwrap().as_f64(), Some(2.0));
+
+        // attribute order does not matter
+        let reordered: OffXml = r#"<SMIRNOFF version="0.3"><Bonds>
+            <Bond k="1.0 * mole ** -1" smirks="[#6:1]-[#6:2]"/>
+            <Bond smirks="[#6:1]-[#1:2]" k="1.0 * mole ** -1"/>
+            </Bonds></SMIRNOFF>"#
+            .parse()
+            .unwrap();
+        assert!(old.diff(&reordered).is_empty());
+
+        // and a missing handler removes all of its parameters
+        let empty: OffXml = r#"<SMIRNOFF version="0.3"/>"#.parse().unwrap();
+        assert_eq!(old.diff(&empty).len(), 2);
+        assert!(old
+            .diff(&empty)
+            .iter()
+            .all(|d| matches!(d, Difference::Removed { .. })));
+    }
+
+    fn invalid(s: &str) -> String {
+        match s.parse::<OffXml>() {
+            Err(Error::InvalidOffxml(msg)) => msg,
+            other => panic!("expected InvalidOffxml, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert!(invalid(r#"<ForceField version="0.3"/>"#)
+            .contains("expected SMIRNOFF"));
+        assert!(invalid(
+            r#"<SMIRNOFF version="0.3"><Bonds><Bond id="b1"/></Bonds>
+            </SMIRNOFF>"#
+        )
+        .contains("without smirks"));
+        assert!(invalid("").contains("missing SMIRNOFF"));
+
+        // truncated anywhere inside the root element
+        let cut = FORCE_FIELD.find("<LibraryCharges").unwrap();
+        assert!(invalid(&FORCE_FIELD[..cut]).contains("end of file"));
+        assert!(invalid(r#"<SMIRNOFF version="0.3">"#).contains("end of file"));
+
+        // input that could not be written back unchanged
+        assert!(invalid(r#"<SMIRNOFF version="0.3"><!-- hi --></SMIRNOFF>"#)
+            .contains("comments"));
+        assert!(invalid(
+            r#"<SMIRNOFF version="0.3"><Bonds>
+            <Bond smirks="[*:1]~[*:2]"><Extra/></Bond></Bonds></SMIRNOFF>"#
+        )
+        .contains("inside a parameter"));
+        assert!(invalid(
+            r#"<SMIRNOFF version="0.3"><Author><Name/></Author></SMIRNOFF>"#
+        )
+        .contains("inside Author"));
+        assert!(invalid(
+            r#"<SMIRNOFF version="0.3"><Bonds>hi</Bonds></SMIRNOFF>"#
+        )
+        .contains("text outside"));
+        assert!(invalid(
+            r#"<SMIRNOFF version="0.3"/><SMIRNOFF version="0.3"/>"#
+        )
+        .contains("multiple root"));
+    }
+}
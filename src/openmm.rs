@@ -1,48 +1,673 @@
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 
-use pyo3::{types::PyModule, IntoPy, Py, PyAny, PyObject, Python};
+use pyo3::{
+    types::{PyModule, PyTuple},
+    FromPyObject, IntoPy, Py, PyAny, PyErr, PyObject, PyResult, Python,
+};
 
-pub struct Modeller;
+use serde::Serialize;
+
+use crate::conformer::Conformer;
+use crate::molecule::Molecule;
+use crate::units::{
+    Concentration, Energy, Force, ForceConstant, Frequency, Length, Mass,
+    Temperature, Time,
+};
+use crate::{Error, Result};
+
+/// a wrapper around `openmm.app.ForceField`, used by [Modeller] to look up
+/// templates, charges, and radii for solvation and protonation. this is
+/// distinct from the SMIRNOFF [crate::forcefield::ForceField]
+pub struct ForceField {
+    inner: Py<PyAny>,
+}
+
+impl ForceField {
+    /// load the OpenMM force field XML files in `files`, like
+    /// `amber14-all.xml` and `amber14/tip3p.xml`
+    pub fn new(files: &[&str]) -> Result<Self> {
+        let inner = Python::with_gil(|py| {
+            let app = PyModule::import(py, "openmm.app")?;
+            Ok::<_, PyErr>(
+                app.getattr("ForceField")?
+                    .call1(PyTuple::new(py, files))?
+                    .into(),
+            )
+        })?;
+        Ok(Self { inner })
+    }
+
+    /// register templates for the small molecules in `molecules`,
+    /// parameterized with the SMIRNOFF force field `forcefield` (like
+    /// `openff-2.1.0`), using `openmmforcefields`. this is required before
+    /// solvating a topology containing a ligand
+    pub fn register_molecules(
+        &mut self,
+        molecules: &[Molecule],
+        forcefield: &str,
+    ) -> Result<()> {
+        Python::with_gil(|py| {
+            let register = PyModule::from_code(
+                py,
+                "def register(ff, molecules, forcefield):
+    from openmmforcefields.generators import SMIRNOFFTemplateGenerator
+    generator = SMIRNOFFTemplateGenerator(
+        molecules=molecules, forcefield=forcefield
+    )
+    ff.registerTemplateGenerator(generator.generator)
+",
+                "",
+                "",
+            )?
+            .getattr("register")?;
+            let molecules: Vec<_> =
+                molecules.iter().map(|m| m.inner.clone_ref(py)).collect();
+            register.call1((&self.inner, molecules, forcefield))?;
+            Ok(())
+        })
+    }
+}
+
+/// the water models supported by [Modeller::add_solvent]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WaterModel {
+    #[default]
+    Tip3p,
+    Spce,
+    Tip4pEw,
+    Tip5p,
+    Swm4Ndp,
+}
+
+impl WaterModel {
+    /// the name OpenMM uses for this model
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WaterModel::Tip3p => "tip3p",
+            WaterModel::Spce => "spce",
+            WaterModel::Tip4pEw => "tip4pew",
+            WaterModel::Tip5p => "tip5p",
+            WaterModel::Swm4Ndp => "swm4ndp",
+        }
+    }
+}
+
+/// options for [Modeller::add_solvent]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolventOptions {
+    /// the minimum distance between the solute and the edge of the box
+    pub padding: Length,
+
+    /// the concentration of salt to add
+    pub ionic_strength: Concentration,
+
+    pub model: WaterModel,
+
+    /// the positive ion to add, like `Na+` or `K+`
+    pub positive_ion: String,
+
+    /// the negative ion to add, like `Cl-`
+    pub negative_ion: String,
+
+    /// whether to add extra ions to neutralize the system
+    pub neutralize: bool,
+}
+
+impl Default for SolventOptions {
+    fn default() -> Self {
+        Self {
+            padding: Length::angstroms(10.0),
+            ionic_strength: Concentration::molar(0.0),
+            model: WaterModel::default(),
+            positive_ion: String::from("Na+"),
+            negative_ion: String::from("Cl-"),
+            neutralize: true,
+        }
+    }
+}
+
+/// a wrapper around `openmm.app.Modeller` for editing a topology and its
+/// positions
+pub struct Modeller {
+    inner: Py<PyAny>,
+}
 
 impl Modeller {
-    pub fn new(_topology: Topology, _positions: ()) -> Self {
-        todo!()
+    /// construct a modeller from `topology` and the `positions` of its atoms
+    pub fn new(topology: Topology, positions: Conformer) -> Result<Self> {
+        positions.check_atoms(topology.n_atoms()?)?;
+        Python::with_gil(|py| {
+            let new = PyModule::from_code(
+                py,
+                "def new(topology, positions):
+    import openmm
+    from openmm.app import Modeller
+    positions = [openmm.Vec3(*p) for p in positions.tolist()]
+    return Modeller(topology, positions * openmm.unit.angstrom)
+",
+                "",
+                "",
+            )?
+            .getattr("new")?;
+            let inner = new.call1((topology.inner, positions))?.into();
+            Ok(Self { inner })
+        })
+    }
+
+    /// fill a rectangular box around the current topology with water and
+    /// ions, as described by `options`. `forcefield` must have templates for
+    /// every residue, including any ligands
+    pub fn add_solvent(
+        &mut self,
+        forcefield: &ForceField,
+        options: &SolventOptions,
+    ) -> Result<()> {
+        Python::with_gil(|py| {
+            let add_solvent = PyModule::from_code(
+                py,
+                "def add_solvent(
+    modeller, ff, padding, ionic_strength, model, positive, negative, neutralize
+):
+    import openmm
+    modeller.addSolvent(
+        ff,
+        model=model,
+        padding=padding * openmm.unit.angstrom,
+        ionicStrength=ionic_strength * openmm.unit.molar,
+        positiveIon=positive,
+        negativeIon=negative,
+        neutralize=neutralize,
+    )
+",
+                "",
+                "",
+            )?
+            .getattr("add_solvent")?;
+            add_solvent.call1((
+                &self.inner,
+                &forcefield.inner,
+                options.padding.as_angstroms(),
+                options.ionic_strength.as_molar(),
+                options.model.as_str(),
+                &options.positive_ion,
+                &options.negative_ion,
+                options.neutralize,
+            ))?;
+            Ok(())
+        })
+    }
+
+    /// add missing hydrogens to the protein and water residues in the
+    /// topology, choosing protonation states appropriate for `ph`. if
+    /// `forcefield` is given, it is used to add hydrogens to other residues
+    /// with matching templates
+    pub fn add_hydrogens(
+        &mut self,
+        forcefield: Option<&ForceField>,
+        ph: f64,
+    ) -> Result<()> {
+        Python::with_gil(|py| {
+            let forcefield = forcefield.map(|ff| ff.inner.clone_ref(py));
+            self.inner
+                .call_method1(py, "addHydrogens", (forcefield, ph))?;
+            Ok(())
+        })
+    }
+
+    /// delete the atoms with indices in `atoms`, along with any bonds
+    /// involving them
+    pub fn delete(&mut self, atoms: &[usize]) -> Result<()> {
+        Python::with_gil(|py| {
+            let delete = PyModule::from_code(
+                py,
+                "def delete(modeller, indices):
+    indices = set(indices)
+    modeller.delete(
+        [a for a in modeller.topology.atoms() if a.index in indices]
+    )
+",
+                "",
+                "",
+            )?
+            .getattr("delete")?;
+            delete.call1((&self.inner, atoms.to_vec()))?;
+            Ok(())
+        })
     }
 
-    pub fn get_topology(&self) -> Topology {
-        todo!()
+    pub fn get_topology(&self) -> Result<Topology> {
+        let inner = Python::with_gil(|py| self.inner.getattr(py, "topology"))?;
+        Ok(Topology { inner })
+    }
+
+    pub fn get_positions(&self) -> Result<Conformer> {
+        Python::with_gil(|py| {
+            let get_positions = PyModule::from_code(
+                py,
+                "def get_positions(modeller):
+    import numpy as np
+    import openmm
+    ret = modeller.getPositions().value_in_unit(openmm.unit.angstrom)
+    return np.array([[v.x, v.y, v.z] for v in ret], dtype=float)
+",
+                "",
+                "",
+            )?
+            .getattr("get_positions")?;
+            Ok(get_positions.call1((&self.inner,))?.extract()?)
+        })
     }
 }
 
-pub struct Topology;
+pub struct Topology {
+    pub(crate) inner: Py<PyAny>,
+}
+
+impl IntoPy<Py<PyAny>> for Topology {
+    fn into_py(self, _py: Python<'_>) -> Py<PyAny> {
+        self.inner
+    }
+}
 
 impl Topology {
-    pub fn atoms(&self) -> () {
-        todo!()
+    pub fn n_atoms(&self) -> Result<usize> {
+        Python::with_gil(|py| {
+            Ok(self.inner.call_method0(py, "getNumAtoms")?.extract(py)?)
+        })
+    }
+
+    pub fn chains(&self) -> Result<Vec<Chain>> {
+        self.extract(
+            "def get_chains(top):
+    return [{'index': c.index, 'id': c.id} for c in top.chains()]
+",
+            "get_chains",
+        )
+    }
+
+    pub fn residues(&self) -> Result<Vec<Residue>> {
+        self.extract(
+            "def get_residues(top):
+    return [
+        {
+            'index': r.index,
+            'name': r.name,
+            'id': r.id,
+            'chain': r.chain.index,
+        }
+        for r in top.residues()
+    ]
+",
+            "get_residues",
+        )
+    }
+
+    pub fn atoms(&self) -> Result<Vec<Atom>> {
+        self.extract(
+            "def get_atoms(top):
+    return [
+        {
+            'index': a.index,
+            'name': a.name,
+            'element': None if a.element is None else a.element.symbol,
+            'residue': a.residue.index,
+            'residue_name': a.residue.name,
+            'residue_id': a.residue.id,
+            'chain': a.residue.chain.index,
+        }
+        for a in top.atoms()
+    ]
+",
+            "get_atoms",
+        )
+    }
+
+    /// return the bonds in `self` as pairs of atom indices
+    pub fn bonds(&self) -> Result<Vec<(usize, usize)>> {
+        self.extract(
+            "def get_bonds(top):
+    return [(b[0].index, b[1].index) for b in top.bonds()]
+",
+            "get_bonds",
+        )
+    }
+
+    /// return the periodic box vectors, or `None` if `self` is not periodic
+    pub fn periodic_box_vectors(&self) -> Result<Option<[[Length; 3]; 3]>> {
+        self.extract(
+            "def get_box_vectors(top):
+    import openmm
+    box = top.getPeriodicBoxVectors()
+    if box is None:
+        return None
+    box = box.value_in_unit(openmm.unit.angstrom)
+    return [[v.x, v.y, v.z] for v in box]
+",
+            "get_box_vectors",
+        )
     }
+
+    /// return a new topology containing only the atoms in `atoms` and the
+    /// bonds between them, like the atoms of a ligand in a protein-ligand
+    /// complex. atoms are renumbered in their original order, and chains and
+    /// residues left empty are dropped
+    pub fn subset(&self, atoms: &[usize]) -> Result<Topology> {
+        Python::with_gil(|py| {
+            let fun = PyModule::from_code(
+                py,
+                "def subset(top, indices):
+    from openmm.app import Topology
+    keep = set(indices)
+    new = Topology()
+    atom_map = {}
+    for chain in top.chains():
+        c = None
+        for residue in chain.residues():
+            r = None
+            for atom in residue.atoms():
+                if atom.index not in keep:
+                    continue
+                if c is None:
+                    c = new.addChain(chain.id)
+                if r is None:
+                    r = new.addResidue(
+                        residue.name, c, residue.id, residue.insertionCode
+                    )
+                atom_map[atom] = new.addAtom(atom.name, atom.element, r, atom.id)
+    for bond in top.bonds():
+        a, b = bond[0], bond[1]
+        if a in atom_map and b in atom_map:
+            new.addBond(atom_map[a], atom_map[b], bond.type, bond.order)
+    new.setPeriodicBoxVectors(top.getPeriodicBoxVectors())
+    return new
+",
+                "",
+                "",
+            )?
+            .getattr("subset")?;
+            let inner = fun.call1((&self.inner, atoms.to_vec()))?.into();
+            Ok(Topology { inner })
+        })
+    }
+
+    /// helper method for running the Python function `fun` defined in `code`
+    /// on `self` and extracting the result
+    fn extract<T>(&self, code: &str, fun: &str) -> Result<T>
+    where
+        T: for<'a> FromPyObject<'a>,
+    {
+        Python::with_gil(|py| {
+            let fun = PyModule::from_code(py, code, "", "")?.getattr(fun)?;
+            Ok(fun.call1((&self.inner,))?.extract()?)
+        })
+    }
+}
+
+#[derive(FromPyObject, Clone, Debug, PartialEq)]
+pub struct Chain {
+    #[pyo3(item)]
+    pub index: usize,
+    #[pyo3(item)]
+    pub id: String,
+}
+
+#[derive(FromPyObject, Clone, Debug, PartialEq)]
+pub struct Residue {
+    #[pyo3(item)]
+    pub index: usize,
+    #[pyo3(item)]
+    pub name: String,
+    /// the residue number from the input file, which need not be numeric
+    #[pyo3(item)]
+    pub id: String,
+    /// the index of the containing [Chain]
+    #[pyo3(item)]
+    pub chain: usize,
 }
 
-pub struct PDBFile;
+#[derive(FromPyObject, Clone, Debug, PartialEq)]
+pub struct Atom {
+    #[pyo3(item)]
+    pub index: usize,
+    #[pyo3(item)]
+    pub name: String,
+    /// the element symbol, or `None` for virtual sites
+    #[pyo3(item)]
+    pub element: Option<String>,
+    /// the index of the containing [Residue]
+    #[pyo3(item)]
+    pub residue: usize,
+    #[pyo3(item)]
+    pub residue_name: String,
+    #[pyo3(item)]
+    pub residue_id: String,
+    /// the index of the containing [Chain]
+    #[pyo3(item)]
+    pub chain: usize,
+}
+
+pub struct PDBFile {
+    inner: Py<PyAny>,
+}
 
 impl PDBFile {
-    pub fn new(_filename: impl AsRef<Path>) -> Self {
-        todo!()
+    /// load the PDB file at `filename` with `openmm.app.PDBFile`
+    pub fn new(filename: impl AsRef<Path>) -> Result<Self> {
+        let inner = Python::with_gil(|py| {
+            let app = PyModule::import(py, "openmm.app")?;
+            Ok::<_, PyErr>(
+                app.getattr("PDBFile")?.call1((filename.as_ref(),))?.into(),
+            )
+        })?;
+        Ok(Self { inner })
     }
 
-    pub fn topology(&self) -> Topology {
-        todo!();
+    pub fn topology(&self) -> Result<Topology> {
+        let inner = Python::with_gil(|py| self.inner.getattr(py, "topology"))?;
+        Ok(Topology { inner })
     }
 
-    pub fn positions(&self) {
-        todo!();
+    /// return the positions of the atoms in the file
+    pub fn positions(&self) -> Result<Conformer> {
+        Python::with_gil(|py| {
+            let get_positions = PyModule::from_code(
+                py,
+                "def get_positions(pdb):
+    import openmm
+    return pdb.getPositions(asNumpy=True).value_in_unit(openmm.unit.angstrom)
+",
+                "",
+                "",
+            )?
+            .getattr("get_positions")?;
+            Ok(get_positions.call1((&self.inner,))?.extract()?)
+        })
+    }
+
+    /// write the current coordinates of `context` to `filename` in PDB format,
+    /// using `topology` for the atom, residue, and chain information
+    pub fn write(
+        topology: &Topology,
+        context: &Context,
+        filename: impl AsRef<Path>,
+    ) -> Result<()> {
+        Python::with_gil(|py| {
+            let write_pdb = PyModule::from_code(
+                py,
+                "def write_pdb(topology, ctx, filename):
+    from openmm.app import PDBFile
+    positions = ctx.getState(getPositions=True).getPositions()
+    with open(filename, 'w') as out:
+        PDBFile.writeFile(topology, positions, out)
+",
+                "",
+                "",
+            )?
+            .getattr("write_pdb")?;
+            write_pdb.call1((
+                &topology.inner,
+                &context.inner,
+                filename.as_ref(),
+            ))?;
+            Ok(())
+        })
+    }
+}
+
+/// periodic output attached to a [Simulation] with
+/// [Simulation::add_reporter]. `interval` is the number of steps between
+/// reports
+pub enum Reporter {
+    /// the step, time, energies (in kJ/mol), and temperature as CSV
+    EnergyCsv { path: PathBuf, interval: usize },
+
+    /// a trajectory in DCD format
+    Dcd { path: PathBuf, interval: usize },
+
+    /// a trajectory in XTC format
+    Xtc { path: PathBuf, interval: usize },
+
+    /// a checkpoint file for restarting the simulation, overwritten at every
+    /// report
+    Checkpoint { path: PathBuf, interval: usize },
+}
+
+impl Reporter {
+    /// construct the corresponding OpenMM reporter
+    fn to_py(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let make_reporter = PyModule::from_code(
+            py,
+            "def make_reporter(kind, path, interval):
+    from openmm import app
+    if kind == 'energy_csv':
+        return app.StateDataReporter(
+            path,
+            interval,
+            step=True,
+            time=True,
+            potentialEnergy=True,
+            kineticEnergy=True,
+            totalEnergy=True,
+            temperature=True,
+            separator=',',
+        )
+    elif kind == 'dcd':
+        return app.DCDReporter(path, interval)
+    elif kind == 'xtc':
+        return app.XTCReporter(path, interval)
+    elif kind == 'checkpoint':
+        return app.CheckpointReporter(path, interval)
+",
+            "",
+            "",
+        )?
+        .getattr("make_reporter")?;
+        let (kind, path, interval) = match self {
+            Reporter::EnergyCsv { path, interval } => {
+                ("energy_csv", path, interval)
+            }
+            Reporter::Dcd { path, interval } => ("dcd", path, interval),
+            Reporter::Xtc { path, interval } => ("xtc", path, interval),
+            Reporter::Checkpoint { path, interval } => {
+                ("checkpoint", path, interval)
+            }
+        };
+        Ok(make_reporter.call1((kind, path, *interval))?.into())
     }
 }
 
-pub struct Simulation;
+/// a wrapper around `openmm.app.Simulation` for running dynamics
+pub struct Simulation {
+    inner: Py<PyAny>,
+}
 
 impl Simulation {
-    pub fn context(&self) -> Context {
-        todo!();
+    pub fn new(
+        topology: Topology,
+        system: System,
+        integrator: Integrator,
+        platform: Platform,
+    ) -> Result<Self> {
+        let inner = Python::with_gil(|py| {
+            let app = PyModule::import(py, "openmm.app")?;
+            let (platform, properties) = platform.to_py(py)?;
+            Ok::<_, Error>(
+                app.getattr("Simulation")?
+                    .call1((
+                        topology.inner,
+                        system.inner,
+                        integrator.to_py(py)?,
+                        platform,
+                        properties,
+                    ))?
+                    .into(),
+            )
+        })?;
+        Ok(Self { inner })
+    }
+
+    /// return the [Context] driven by `self`. changes made through the
+    /// returned context, like setting positions, are visible to the
+    /// simulation
+    pub fn context(&self) -> Result<Context> {
+        let inner = Python::with_gil(|py| self.inner.getattr(py, "context"))?;
+        Ok(Context { inner })
+    }
+
+    /// advance the simulation by `steps` integrator steps, invoking any
+    /// reporters along the way
+    pub fn step(&mut self, steps: usize) -> Result<()> {
+        Python::with_gil(|py| {
+            self.inner.call_method1(py, "step", (steps,))?;
+            Ok(())
+        })
+    }
+
+    /// minimize the current positions until the root-mean-square force is
+    /// below `tolerance`, or for at most `max_iterations` steps. a
+    /// `max_iterations` of 0 runs until convergence
+    pub fn minimize_energy(
+        &mut self,
+        tolerance: Force,
+        max_iterations: usize,
+    ) -> Result<()> {
+        Python::with_gil(|py| {
+            let minimize = PyModule::from_code(
+                py,
+                "def minimize(sim, tolerance, max_iterations):
+    import openmm
+    unit = openmm.unit.kilojoule_per_mole / openmm.unit.nanometer
+    sim.minimizeEnergy(
+        tolerance=tolerance * unit, maxIterations=max_iterations
+    )
+",
+                "",
+                "",
+            )?
+            .getattr("minimize")?;
+            minimize.call1((
+                &self.inner,
+                tolerance.as_kj_per_mol_per_nanometer(),
+                max_iterations,
+            ))?;
+            Ok(())
+        })
+    }
+
+    /// the number of steps taken so far
+    pub fn current_step(&self) -> Result<usize> {
+        Python::with_gil(|py| {
+            Ok(self.inner.getattr(py, "currentStep")?.extract(py)?)
+        })
+    }
+
+    pub fn add_reporter(&mut self, reporter: Reporter) -> Result<()> {
+        Python::with_gil(|py| {
+            let reporters = self.inner.getattr(py, "reporters")?;
+            reporters.call_method1(py, "append", (reporter.to_py(py)?,))?;
+            Ok(())
+        })
     }
 }
 
@@ -50,54 +675,432 @@ pub struct System {
     pub(crate) inner: Py<PyAny>,
 }
 
+impl System {
+    /// put each force in `self` into the force group for its [EnergyTerm],
+    /// so that [Context::get_energy_decomposition] can report the energy of
+    /// each term separately. this must be called before `self` is used to
+    /// construct a [Context]
+    pub fn assign_force_groups(&mut self) -> Result<()> {
+        Python::with_gil(|py| {
+            let module = PyModule::from_code(
+                py,
+                "def get_forces(system):
+    return [(f.__class__.__name__, f.getName()) for f in system.getForces()]
+
+def set_groups(system, groups):
+    for force, group in zip(system.getForces(), groups):
+        force.setForceGroup(group)
+",
+                "",
+                "",
+            )?;
+            let forces: Vec<(String, String)> = module
+                .getattr("get_forces")?
+                .call1((&self.inner,))?
+                .extract()?;
+            let split_nonbonded = forces
+                .iter()
+                .any(|(class, _)| class == "CustomNonbondedForce");
+            let groups: Vec<usize> = forces
+                .iter()
+                .map(|(class, name)| {
+                    EnergyTerm::classify(class, name, split_nonbonded).group()
+                })
+                .collect();
+            module.getattr("set_groups")?.call1((&self.inner, groups))?;
+            Ok(())
+        })
+    }
+}
+
+/// the terms of a decomposed potential energy, as reported by
+/// [Context::get_energy_decomposition]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EnergyTerm {
+    Bond,
+    Angle,
+    ProperTorsion,
+    ImproperTorsion,
+    /// van der Waals interactions, when they are separate from electrostatics
+    VdW,
+    /// electrostatic interactions, when they are separate from vdW
+    Electrostatics,
+    /// combined vdW and electrostatic interactions, when both are handled by
+    /// a single `NonbondedForce`
+    Nonbonded,
+    /// any other force, or a force that was never assigned a group
+    Other,
+}
+
+impl EnergyTerm {
+    pub const ALL: [EnergyTerm; 8] = [
+        EnergyTerm::Bond,
+        EnergyTerm::Angle,
+        EnergyTerm::ProperTorsion,
+        EnergyTerm::ImproperTorsion,
+        EnergyTerm::VdW,
+        EnergyTerm::Electrostatics,
+        EnergyTerm::Nonbonded,
+        EnergyTerm::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnergyTerm::Bond => "Bond",
+            EnergyTerm::Angle => "Angle",
+            EnergyTerm::ProperTorsion => "ProperTorsion",
+            EnergyTerm::ImproperTorsion => "ImproperTorsion",
+            EnergyTerm::VdW => "VdW",
+            EnergyTerm::Electrostatics => "Electrostatics",
+            EnergyTerm::Nonbonded => "Nonbonded",
+            EnergyTerm::Other => "Other",
+        }
+    }
+
+    /// the force group used for this term. group 0, the OpenMM default, is
+    /// reserved for [EnergyTerm::Other] so that forces without an assigned
+    /// group are not mistaken for bonds
+    fn group(self) -> usize {
+        match self {
+            EnergyTerm::Other => 0,
+            t => t as usize + 1,
+        }
+    }
+
+    fn from_group(group: usize) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|t| t.group() == group)
+            .unwrap_or(EnergyTerm::Other)
+    }
+
+    /// determine the term for a force from its Python `class` name and its
+    /// `name`. `split_nonbonded` says whether vdW interactions have been
+    /// separated from the `NonbondedForce`, as in the output of
+    /// [crate::forcefield::Interchange::to_openmm_decomposed]
+    fn classify(class: &str, name: &str, split_nonbonded: bool) -> Self {
+        match class {
+            "HarmonicBondForce" => EnergyTerm::Bond,
+            "HarmonicAngleForce" => EnergyTerm::Angle,
+            "PeriodicTorsionForce" if name == IMPROPER_FORCE_NAME => {
+                EnergyTerm::ImproperTorsion
+            }
+            "PeriodicTorsionForce" => EnergyTerm::ProperTorsion,
+            "NonbondedForce" if split_nonbonded => EnergyTerm::Electrostatics,
+            "NonbondedForce" => EnergyTerm::Nonbonded,
+            _ if name.starts_with("vdW") => EnergyTerm::VdW,
+            _ if name.starts_with("Electrostatics") => {
+                EnergyTerm::Electrostatics
+            }
+            "CustomNonbondedForce" => EnergyTerm::VdW,
+            _ => EnergyTerm::Other,
+        }
+    }
+}
+
+/// the name given to the separate improper torsion force created by
+/// [crate::forcefield::Interchange::to_openmm_decomposed]
+pub(crate) const IMPROPER_FORCE_NAME: &str = "ImproperTorsionForce";
+
+impl Serialize for EnergyTerm {
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
 pub enum Integrator {
-    /// time step in femtoseconds
-    Verlet(f64),
+    /// a constant-energy integrator with a fixed time step
+    Verlet(Time),
+
+    /// a Langevin integrator using the LFMiddle discretization, for
+    /// sampling at constant temperature
+    LangevinMiddle {
+        temperature: Temperature,
+        friction: Frequency,
+        timestep: Time,
+        /// the random number seed, or `None` to pick a different seed for
+        /// every run
+        seed: Option<i32>,
+    },
+
+    /// a Brownian dynamics integrator
+    Brownian {
+        temperature: Temperature,
+        friction: Frequency,
+        timestep: Time,
+        seed: Option<i32>,
+    },
+
+    /// a Verlet integrator that adjusts its time step to keep the integration
+    /// error below `error_tolerance`
+    VariableVerlet { error_tolerance: f64 },
+
+    /// a Langevin integrator that adjusts its time step to keep the
+    /// integration error below `error_tolerance`
+    VariableLangevin {
+        temperature: Temperature,
+        friction: Frequency,
+        error_tolerance: f64,
+        seed: Option<i32>,
+    },
 }
 
-impl IntoPy<PyObject> for Integrator {
-    fn into_py(self, py: Python<'_>) -> PyObject {
-        let openmm = PyModule::from_code(
+impl Integrator {
+    /// construct the corresponding OpenMM integrator
+    fn to_py(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let make_integrator = PyModule::from_code(
             py,
-            "def make_integrator(time_step):
+            "def make_integrator(
+    kind, temperature, friction, timestep, error_tolerance, seed
+):
     import openmm
-    return openmm.VerletIntegrator(time_step * openmm.unit.femtoseconds)",
+    from openmm import unit
+    if temperature is not None:
+        temperature *= unit.kelvin
+    if friction is not None:
+        friction /= unit.picosecond
+    if timestep is not None:
+        timestep *= unit.picoseconds
+    if kind == 'verlet':
+        integrator = openmm.VerletIntegrator(timestep)
+    elif kind == 'langevin_middle':
+        integrator = openmm.LangevinMiddleIntegrator(
+            temperature, friction, timestep
+        )
+    elif kind == 'brownian':
+        integrator = openmm.BrownianIntegrator(temperature, friction, timestep)
+    elif kind == 'variable_verlet':
+        integrator = openmm.VariableVerletIntegrator(error_tolerance)
+    elif kind == 'variable_langevin':
+        integrator = openmm.VariableLangevinIntegrator(
+            temperature, friction, error_tolerance
+        )
+    if seed is not None:
+        integrator.setRandomNumberSeed(seed)
+    return integrator
+",
             "",
             "",
-        )
-        .unwrap()
-        .getattr("make_integrator")
-        .unwrap();
+        )?
+        .getattr("make_integrator")?;
+        let args = match *self {
+            Integrator::Verlet(timestep) => (
+                "verlet",
+                None,
+                None,
+                Some(timestep.as_picoseconds()),
+                None,
+                None,
+            ),
+            Integrator::LangevinMiddle {
+                temperature,
+                friction,
+                timestep,
+                seed,
+            } => (
+                "langevin_middle",
+                Some(temperature.as_kelvin()),
+                Some(friction.as_per_picosecond()),
+                Some(timestep.as_picoseconds()),
+                None,
+                seed,
+            ),
+            Integrator::Brownian {
+                temperature,
+                friction,
+                timestep,
+                seed,
+            } => (
+                "brownian",
+                Some(temperature.as_kelvin()),
+                Some(friction.as_per_picosecond()),
+                Some(timestep.as_picoseconds()),
+                None,
+                seed,
+            ),
+            Integrator::VariableVerlet { error_tolerance } => (
+                "variable_verlet",
+                None,
+                None,
+                None,
+                Some(error_tolerance),
+                None,
+            ),
+            Integrator::VariableLangevin {
+                temperature,
+                friction,
+                error_tolerance,
+                seed,
+            } => (
+                "variable_langevin",
+                Some(temperature.as_kelvin()),
+                Some(friction.as_per_picosecond()),
+                None,
+                Some(error_tolerance),
+                seed,
+            ),
+        };
+        Ok(make_integrator.call1(args)?.into())
+    }
+}
+
+/// the floating point precision used by the GPU platforms
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Precision {
+    #[default]
+    Single,
+    Mixed,
+    Double,
+}
+
+impl Precision {
+    fn as_str(&self) -> &'static str {
         match self {
-            Integrator::Verlet(time_step) => {
-                openmm.call1((time_step,)).unwrap().into()
-            }
+            Precision::Single => "single",
+            Precision::Mixed => "mixed",
+            Precision::Double => "double",
         }
     }
 }
 
+/// an OpenMM platform along with the properties used to configure it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Platform {
     Reference,
+
+    Cpu {
+        /// the number of threads to use, or `None` for one per core
+        threads: Option<usize>,
+    },
+
+    Cuda {
+        precision: Precision,
+        /// the index of the GPU to use, or `None` to let OpenMM choose
+        device_index: Option<usize>,
+    },
+
+    OpenCL {
+        precision: Precision,
+        device_index: Option<usize>,
+    },
 }
 
 impl Platform {
-    pub fn by_name(_name: &str) -> Self {
-        todo!();
+    /// the names of the platforms available in the current OpenMM
+    /// installation, like `Reference` and `CPU`
+    pub fn available() -> Result<Vec<String>> {
+        Python::with_gil(|py| {
+            let available = PyModule::from_code(
+                py,
+                "def available():
+    import openmm
+    return [
+        openmm.Platform.getPlatform(i).getName()
+        for i in range(openmm.Platform.getNumPlatforms())
+    ]
+",
+                "",
+                "",
+            )?
+            .getattr("available")?;
+            Ok(available.call0()?.extract()?)
+        })
     }
-}
 
-impl IntoPy<PyObject> for Platform {
-    fn into_py(self, py: Python<'_>) -> PyObject {
-        let openmm = PyModule::import(py, "openmm").unwrap();
+    /// return the platform named `name`, ignoring case, with its default
+    /// properties. returns [Error::MissingPlatform] if no such platform is
+    /// available
+    pub fn by_name(name: &str) -> Result<Self> {
+        let platform = match name.to_lowercase().as_str() {
+            "reference" => Platform::Reference,
+            "cpu" => Platform::Cpu { threads: None },
+            "cuda" => Platform::Cuda {
+                precision: Precision::default(),
+                device_index: None,
+            },
+            "opencl" => Platform::OpenCL {
+                precision: Precision::default(),
+                device_index: None,
+            },
+            _ => {
+                return Err(Error::MissingPlatform {
+                    name: name.to_owned(),
+                    available: Self::available()?,
+                })
+            }
+        };
+        platform.check_available()?;
+        Ok(platform)
+    }
+
+    /// the name OpenMM uses for this platform
+    pub fn name(&self) -> &'static str {
         match self {
-            Platform::Reference => {
-                let platform = openmm.getattr("Platform").unwrap();
-                platform
-                    .call_method1("getPlatformByName", ("Reference",))
-                    .unwrap()
-                    .into()
+            Platform::Reference => "Reference",
+            Platform::Cpu { .. } => "CPU",
+            Platform::Cuda { .. } => "CUDA",
+            Platform::OpenCL { .. } => "OpenCL",
+        }
+    }
+
+    /// the platform-specific properties to pass to OpenMM
+    fn properties(&self) -> HashMap<&'static str, String> {
+        let mut props = HashMap::new();
+        match *self {
+            Platform::Reference => {}
+            Platform::Cpu { threads } => {
+                if let Some(threads) = threads {
+                    props.insert("Threads", threads.to_string());
+                }
+            }
+            Platform::Cuda {
+                precision,
+                device_index,
+            }
+            | Platform::OpenCL {
+                precision,
+                device_index,
+            } => {
+                props.insert("Precision", precision.as_str().to_owned());
+                if let Some(index) = device_index {
+                    props.insert("DeviceIndex", index.to_string());
+                }
             }
         }
+        props
+    }
+
+    fn check_available(&self) -> Result<()> {
+        let available = Self::available()?;
+        if available.iter().any(|a| a == self.name()) {
+            Ok(())
+        } else {
+            Err(Error::MissingPlatform {
+                name: self.name().to_owned(),
+                available,
+            })
+        }
+    }
+
+    /// look up the corresponding OpenMM platform, returning it along with its
+    /// properties
+    fn to_py(
+        self,
+        py: Python<'_>,
+    ) -> Result<(PyObject, HashMap<&'static str, String>)> {
+        self.check_available()?;
+        let openmm = PyModule::import(py, "openmm")?;
+        let platform = openmm
+            .getattr("Platform")?
+            .call_method1("getPlatformByName", (self.name(),))?
+            .into();
+        Ok((platform, self.properties()))
     }
 }
 
@@ -110,81 +1113,309 @@ impl Context {
         system: System,
         integrator: Integrator,
         platform: Platform,
-    ) -> Self {
+    ) -> Result<Self> {
         let inner = Python::with_gil(|py| {
-            let openmm = PyModule::import(py, "openmm").unwrap();
-            openmm
-                .getattr("Context")
-                .unwrap()
-                .call1((system.inner, integrator, platform))
-                .unwrap()
-                .into()
-        });
-        Context { inner }
-    }
-
-    /// call `self.setPositions` with `positions` assuming input in Bohr
-    pub fn set_positions(&mut self, positions: Vec<f64>) {
-        let positions: Vec<[f64; 3]> = positions
-            .array_chunks::<3>()
-            .map(|s| s.to_owned())
-            .collect();
+            let openmm = PyModule::import(py, "openmm")?;
+            let (platform, properties) = platform.to_py(py)?;
+            Ok::<_, Error>(
+                openmm
+                    .getattr("Context")?
+                    .call1((
+                        system.inner,
+                        integrator.to_py(py)?,
+                        platform,
+                        properties,
+                    ))?
+                    .into(),
+            )
+        })?;
+        Ok(Context { inner })
+    }
+
+    /// call `self.setPositions` with `positions`, returning
+    /// [Error::AtomCountMismatch] unless there is one position for each
+    /// particle in the [System]
+    pub fn set_positions(&mut self, positions: Conformer) -> Result<()> {
+        positions.check_atoms(self.n_particles()?)?;
         Python::with_gil(|py| {
             let set_positions = PyModule::from_code(
                 py,
                 "def set_positions(ctx, positions):
     import openmm
-    positions = (positions * openmm.unit.bohr).in_units_of(openmm.unit.nanometer)
+    positions = positions * openmm.unit.angstrom
     ctx.setPositions(positions)
 ",
                 "",
                 "",
+            )?
+            .getattr("set_positions")?;
+            set_positions.call1((&self.inner, positions))?;
+            Ok(())
+        })
+    }
+
+    /// like [Context::set_positions], but with `positions` holding only the
+    /// real atoms, in order, and not any virtual sites in the [System]. the
+    /// virtual sites are then placed from the atoms with
+    /// `computeVirtualSites`. returns [Error::AtomCountMismatch] unless
+    /// there is one position for each atom
+    pub fn set_atom_positions(&mut self, positions: Conformer) -> Result<()> {
+        let n_atoms: usize = Python::with_gil(|py| {
+            let set_atom_positions = PyModule::from_code(
+                py,
+                "def set_atom_positions(ctx, positions):
+    import openmm
+    system = ctx.getSystem()
+    n = system.getNumParticles()
+    atoms = [i for i in range(n) if not system.isVirtualSite(i)]
+    if len(atoms) != len(positions):
+        return len(atoms)
+    xyz = [openmm.Vec3(0.0, 0.0, 0.0)] * n
+    for i, p in zip(atoms, positions.tolist()):
+        xyz[i] = openmm.Vec3(*p)
+    ctx.setPositions(xyz * openmm.unit.angstrom)
+    ctx.computeVirtualSites()
+    return len(atoms)
+",
+                "",
+                "",
+            )?
+            .getattr("set_atom_positions")?;
+            Ok::<_, Error>(
+                set_atom_positions
+                    .call1((&self.inner, &positions))?
+                    .extract()?,
             )
-            .unwrap()
-            .getattr("set_positions")
-            .unwrap();
-            set_positions.call1((&self.inner, positions)).unwrap();
-        });
+        })?;
+        positions.check_atoms(n_atoms)
+    }
+
+    /// return the potential energy of each [EnergyTerm]. the
+    /// [System] used to construct `self` should have been prepared with
+    /// [System::assign_force_groups], otherwise the whole energy is reported
+    /// as [EnergyTerm::Other]
+    pub fn get_energy_decomposition(
+        &self,
+    ) -> Result<BTreeMap<EnergyTerm, Energy>> {
+        let energies: Vec<(usize, Energy)> = Python::with_gil(|py| {
+            let decompose = PyModule::from_code(
+                py,
+                "def decompose(ctx):
+    import openmm
+    groups = sorted(
+        {f.getForceGroup() for f in ctx.getSystem().getForces()}
+    )
+    return [
+        (
+            g,
+            ctx.getState(getEnergy=True, groups={g})
+            .getPotentialEnergy()
+            .value_in_unit(openmm.unit.kilocalorie_per_mole),
+        )
+        for g in groups
+    ]
+",
+                "",
+                "",
+            )?
+            .getattr("decompose")?;
+            Ok::<_, Error>(decompose.call1((&self.inner,))?.extract()?)
+        })?;
+        let mut ret = BTreeMap::new();
+        for (group, energy) in energies {
+            let e = ret.entry(EnergyTerm::from_group(group)).or_default();
+            *e = *e + energy;
+        }
+        Ok(ret)
     }
 
-    /// minimize self using `LocalEnergyMinimizer`
-    pub fn minimize(&mut self, f1: f64, steps: usize) {
+    /// set the velocities of all particles to random values drawn from the
+    /// Maxwell-Boltzmann distribution at `temperature`. pass a `seed` for
+    /// reproducible velocities
+    pub fn set_velocities_to_temperature(
+        &mut self,
+        temperature: Temperature,
+        seed: Option<i32>,
+    ) -> Result<()> {
         Python::with_gil(|py| {
-            let openmm = PyModule::import(py, "openmm").unwrap();
-            let m = openmm.getattr("LocalEnergyMinimizer").unwrap();
-            m.call_method1("minimize", (&self.inner, f1, steps))
-                .unwrap();
-        });
+            let set_velocities = PyModule::from_code(
+                py,
+                "def set_velocities(ctx, temperature, seed):
+    import openmm
+    temperature *= openmm.unit.kelvin
+    if seed is None:
+        ctx.setVelocitiesToTemperature(temperature)
+    else:
+        ctx.setVelocitiesToTemperature(temperature, seed)
+",
+                "",
+                "",
+            )?
+            .getattr("set_velocities")?;
+            set_velocities.call1((
+                &self.inner,
+                temperature.as_kelvin(),
+                seed,
+            ))?;
+            Ok(())
+        })
+    }
+
+    /// minimize self using `LocalEnergyMinimizer` until the root-mean-square
+    /// force is below `tolerance`, or for at most `steps` steps. a `steps` of
+    /// 0 runs until convergence
+    pub fn minimize(&mut self, tolerance: Force, steps: usize) -> Result<()> {
+        Python::with_gil(|py| {
+            let openmm = PyModule::import(py, "openmm")?;
+            let m = openmm.getattr("LocalEnergyMinimizer")?;
+            m.call_method1(
+                "minimize",
+                (&self.inner, tolerance.as_kj_per_mol_per_nanometer(), steps),
+            )?;
+            Ok(())
+        })
+    }
+
+    /// the number of particles in the [System], including virtual sites
+    pub fn n_particles(&self) -> Result<usize> {
+        Python::with_gil(|py| {
+            Ok(self
+                .inner
+                .call_method0(py, "getSystem")?
+                .call_method0(py, "getNumParticles")?
+                .extract(py)?)
+        })
     }
 
-    pub fn get_coordinates(&self) -> Vec<f64> {
+    /// return the current positions
+    pub fn get_coordinates(&self) -> Result<Conformer> {
         Python::with_gil(|py| {
             let get_coordinates = PyModule::from_code(
                 py,
                 "def get_coordinates(ctx):
     import openmm
-    ret = ctx.getState(getPositions=True).getPositions()\
-           .value_in_unit(openmm.unit.angstrom)
-    out = []
-    for vec3 in ret:
-        out.extend([vec3.x, vec3.y, vec3.z])
-    return out
+    return (
+        ctx.getState(getPositions=True)
+        .getPositions(asNumpy=True)
+        .value_in_unit(openmm.unit.angstrom)
+    )
 ",
                 "",
                 "",
-            )
-            .unwrap()
-            .getattr("get_coordinates")
-            .unwrap();
-            get_coordinates
-                .call1((&self.inner,))
-                .unwrap()
-                .extract()
-                .unwrap()
+            )?
+            .getattr("get_coordinates")?;
+            Ok(get_coordinates.call1((&self.inner,))?.extract()?)
         })
     }
 
-    pub fn get_energy(&self) -> f64 {
+    /// return the force on each particle as an `[x, y, z]` vector
+    pub fn get_forces(&self) -> Result<Vec<[Force; 3]>> {
+        let forces: Vec<[Force; 3]> = Python::with_gil(|py| {
+            let get_forces = PyModule::from_code(
+                py,
+                "def get_forces(ctx):
+    import openmm
+    unit = openmm.unit.kilocalorie_per_mole / openmm.unit.angstrom
+    ret = ctx.getState(getForces=True).getForces().value_in_unit(unit)
+    return [[vec3.x, vec3.y, vec3.z] for vec3 in ret]
+",
+                "",
+                "",
+            )?
+            .getattr("get_forces")?;
+            Ok::<_, Error>(get_forces.call1((&self.inner,))?.extract()?)
+        })?;
+        let expected = self.n_particles()?;
+        if forces.len() != expected {
+            return Err(Error::AtomCountMismatch {
+                expected,
+                got: forces.len(),
+            });
+        }
+        Ok(forces)
+    }
+
+    /// return the gradient of the energy with respect to the position of
+    /// each particle. this is the negative of [Context::get_forces]. use
+    /// [Force::as_hartree_per_bohr] for comparison with QM gradients
+    pub fn get_gradient(&self) -> Result<Vec<[Force; 3]>> {
+        Ok(self
+            .get_forces()?
+            .into_iter()
+            .map(|f| f.map(|x| -x))
+            .collect())
+    }
+
+    /// return the mass of each particle in the [System]. virtual sites have
+    /// a mass of zero
+    pub fn get_masses(&self) -> Result<Vec<Mass>> {
+        Python::with_gil(|py| {
+            let get_masses = PyModule::from_code(
+                py,
+                "def get_masses(ctx):
+    import openmm
+    system = ctx.getSystem()
+    return [
+        system.getParticleMass(i).value_in_unit(openmm.unit.dalton)
+        for i in range(system.getNumParticles())
+    ]
+",
+                "",
+                "",
+            )?
+            .getattr("get_masses")?;
+            Ok(get_masses.call1((&self.inner,))?.extract()?)
+        })
+    }
+
+    /// compute the Hessian at the current positions by central finite
+    /// differences of the forces, displacing each coordinate by `step`.
+    /// the result is a flattened, symmetric 3N×3N matrix in row-major order.
+    /// the positions are restored afterward
+    pub fn hessian(&mut self, step: Length) -> Result<Vec<ForceConstant>> {
+        Python::with_gil(|py| {
+            let hessian = PyModule::from_code(
+                py,
+                "def hessian(ctx, step):
+    import numpy as np
+    from openmm import unit
+    x0 = (
+        ctx.getState(getPositions=True)
+        .getPositions(asNumpy=True)
+        .value_in_unit(unit.angstrom)
+    )
+    force_unit = unit.kilocalorie_per_mole / unit.angstrom
+
+    def forces(x):
+        ctx.setPositions(x * unit.angstrom)
+        ctx.computeVirtualSites()
+        f = ctx.getState(getForces=True).getForces(asNumpy=True)
+        return f.value_in_unit(force_unit).flatten()
+
+    n = 3 * len(x0)
+    hess = np.zeros((n, n))
+    for i in range(n):
+        atom, dim = divmod(i, 3)
+        x = x0.copy()
+        x[atom, dim] += step
+        plus = forces(x)
+        x[atom, dim] -= 2 * step
+        minus = forces(x)
+        hess[i] = -(plus - minus) / (2 * step)
+    ctx.setPositions(x0 * unit.angstrom)
+    return (0.5 * (hess + hess.T)).flatten().tolist()
+",
+                "",
+                "",
+            )?
+            .getattr("hessian")?;
+            Ok(hessian
+                .call1((&self.inner, step.as_angstroms()))?
+                .extract()?)
+        })
+    }
+
+    pub fn get_energy(&self) -> Result<Energy> {
         Python::with_gil(|py| {
             let get_energy = PyModule::from_code(
                 py,
@@ -196,11 +1427,67 @@ impl Context {
 ",
                 "",
                 "",
-            )
-            .unwrap()
-            .getattr("get_energy")
-            .unwrap();
-            get_energy.call1((&self.inner,)).unwrap().extract().unwrap()
+            )?
+            .getattr("get_energy")?;
+            Ok(get_energy.call1((&self.inner,))?.extract()?)
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forcefield::ForceField as OpenffForceField;
+    use crate::molecule::Toolkit;
+    use crate::units::{BOHR_TO_ANGSTROM, HARTREE_TO_KCAL_PER_MOL};
+
+    #[test]
+    #[ignore = "requires openff-interchange and OpenMM"]
+    fn forces_and_gradient() {
+        let ff = OpenffForceField::new("openff-2.1.0.offxml").unwrap();
+        let mut mol = Molecule::from_smiles("CCO").unwrap();
+        mol.generate_conformers(1, None, Toolkit::RDKit).unwrap();
+        let interchange =
+            ff.create_interchange(mol.to_topology().unwrap()).unwrap();
+        let mut ctx = Context::new(
+            interchange.to_openmm().unwrap(),
+            Integrator::Verlet(Time::femtoseconds(1.0)),
+            Platform::Reference,
+        )
+        .unwrap();
+        let conformer = mol.get_conformer(0).unwrap();
+        ctx.set_positions(conformer.clone()).unwrap();
+
+        let forces = ctx.get_forces().unwrap();
+        let gradient = ctx.get_gradient().unwrap();
+        assert_eq!(forces.len(), 9);
+        for (f, g) in forces.iter().zip(&gradient) {
+            for i in 0..3 {
+                assert_eq!(f[i], -g[i]);
+                let kcal = g[i].as_kcal_per_mol_per_angstrom();
+                let hartree = g[i].as_hartree_per_bohr();
+                let want = kcal * BOHR_TO_ANGSTROM / HARTREE_TO_KCAL_PER_MOL;
+                assert!((hartree - want).abs() <= 1e-12 * want.abs());
+            }
+        }
+
+        // the gradient agrees with a central difference of the energy, which
+        // checks the units of both
+        let step = 1e-4;
+        let displaced = |dx: f64| {
+            let mut coords = conformer.to_angstroms();
+            coords[2][0] += dx;
+            Conformer::from_angstroms(coords)
+        };
+        ctx.set_positions(displaced(step)).unwrap();
+        let plus = ctx.get_energy().unwrap();
+        ctx.set_positions(displaced(-step)).unwrap();
+        let minus = ctx.get_energy().unwrap();
+        let numerical = (plus - minus).as_kcal_per_mol() / (2.0 * step);
+        let analytic = gradient[2][0].as_kcal_per_mol_per_angstrom();
+        assert!(
+            (numerical - analytic).abs() < 1e-3 * analytic.abs().max(1.0),
+            "numerical {numerical} != analytic {analytic}"
+        );
+    }
+}
@@ -0,0 +1,465 @@
+//! typed physical quantities, so that callers cannot mix up units when
+//! passing values to and from OpenMM and openff-toolkit. each type stores its
+//! value in a single internal unit, given by [Quantity::UNIT], and provides
+//! named constructors and accessors for the other units in common use.
+//! partial charges, which openff-toolkit always reports in units of the
+//! elementary charge, and dimensionless values like scale factors are left
+//! as plain f64
+
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use pyo3::{FromPyObject, PyAny, PyResult};
+
+/// the length of one Bohr radius in Å
+pub const BOHR_TO_ANGSTROM: f64 = 0.529_177_210_903;
+
+/// the energy of one Hartree in kcal/mol
+pub const HARTREE_TO_KCAL_PER_MOL: f64 = 627.509_474_063;
+
+/// the number of kJ in one kcal
+const KCAL_TO_KJ: f64 = 4.184;
+
+/// a typed quantity with a fixed internal unit
+pub trait Quantity: Copy {
+    /// the internal unit of `Self`, spelled the way openff-units parses it
+    const UNIT: &'static str;
+
+    /// construct a quantity from a magnitude in [Quantity::UNIT]
+    fn from_magnitude(value: f64) -> Self;
+
+    /// the magnitude of `self` in [Quantity::UNIT]
+    fn magnitude(&self) -> f64;
+}
+
+/// define a newtype around f64 implementing [Quantity], basic arithmetic, and
+/// extraction from Python floats already in the internal unit
+macro_rules! quantity {
+    ($(#[$meta:meta])* $name:ident, $unit:literal) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+        pub struct $name(f64);
+
+        impl Quantity for $name {
+            const UNIT: &'static str = $unit;
+
+            fn from_magnitude(value: f64) -> Self {
+                Self(value)
+            }
+
+            fn magnitude(&self) -> f64 {
+                self.0
+            }
+        }
+
+        impl Add for $name {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self {
+                Self(self.0 + rhs.0)
+            }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self {
+                Self(self.0 - rhs.0)
+            }
+        }
+
+        impl Neg for $name {
+            type Output = Self;
+
+            fn neg(self) -> Self {
+                Self(-self.0)
+            }
+        }
+
+        impl Mul<f64> for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: f64) -> Self {
+                Self(self.0 * rhs)
+            }
+        }
+
+        impl Div<f64> for $name {
+            type Output = Self;
+
+            fn div(self, rhs: f64) -> Self {
+                Self(self.0 / rhs)
+            }
+        }
+
+        impl<'a> FromPyObject<'a> for $name {
+            fn extract(ob: &'a PyAny) -> PyResult<Self> {
+                Ok(Self(ob.extract()?))
+            }
+        }
+    };
+}
+
+quantity! {
+    /// a length, stored in Å
+    Length, "angstrom"
+}
+
+impl Length {
+    pub fn angstroms(value: f64) -> Self {
+        Self(value)
+    }
+
+    pub fn bohr(value: f64) -> Self {
+        Self(value * BOHR_TO_ANGSTROM)
+    }
+
+    pub fn nanometers(value: f64) -> Self {
+        Self(value * 10.0)
+    }
+
+    pub fn as_angstroms(&self) -> f64 {
+        self.0
+    }
+
+    pub fn as_bohr(&self) -> f64 {
+        self.0 / BOHR_TO_ANGSTROM
+    }
+
+    pub fn as_nanometers(&self) -> f64 {
+        self.0 / 10.0
+    }
+}
+
+quantity! {
+    /// an energy, stored in kcal/mol
+    Energy, "kilocalorie / mole"
+}
+
+impl Energy {
+    pub fn kcal_per_mol(value: f64) -> Self {
+        Self(value)
+    }
+
+    pub fn kj_per_mol(value: f64) -> Self {
+        Self(value / KCAL_TO_KJ)
+    }
+
+    pub fn hartree(value: f64) -> Self {
+        Self(value * HARTREE_TO_KCAL_PER_MOL)
+    }
+
+    pub fn as_kcal_per_mol(&self) -> f64 {
+        self.0
+    }
+
+    pub fn as_kj_per_mol(&self) -> f64 {
+        self.0 * KCAL_TO_KJ
+    }
+
+    pub fn as_hartree(&self) -> f64 {
+        self.0 / HARTREE_TO_KCAL_PER_MOL
+    }
+}
+
+quantity! {
+    /// a force, or a component of an energy gradient, stored in kcal/mol/Å
+    Force, "kilocalorie / mole / angstrom"
+}
+
+impl Force {
+    pub fn kcal_per_mol_per_angstrom(value: f64) -> Self {
+        Self(value)
+    }
+
+    pub fn kj_per_mol_per_nanometer(value: f64) -> Self {
+        Self(value / KCAL_TO_KJ / 10.0)
+    }
+
+    pub fn hartree_per_bohr(value: f64) -> Self {
+        Self(value * HARTREE_TO_KCAL_PER_MOL / BOHR_TO_ANGSTROM)
+    }
+
+    pub fn as_kcal_per_mol_per_angstrom(&self) -> f64 {
+        self.0
+    }
+
+    pub fn as_kj_per_mol_per_nanometer(&self) -> f64 {
+        self.0 * KCAL_TO_KJ * 10.0
+    }
+
+    pub fn as_hartree_per_bohr(&self) -> f64 {
+        self.0 * BOHR_TO_ANGSTROM / HARTREE_TO_KCAL_PER_MOL
+    }
+}
+
+quantity! {
+    /// a bond stretching force constant, or an element of a Cartesian
+    /// Hessian, stored in kcal/mol/Å²
+    ForceConstant, "kilocalorie / mole / angstrom ** 2"
+}
+
+impl ForceConstant {
+    pub fn kcal_per_mol_per_angstrom2(value: f64) -> Self {
+        Self(value)
+    }
+
+    pub fn kj_per_mol_per_nanometer2(value: f64) -> Self {
+        Self(value / KCAL_TO_KJ / 100.0)
+    }
+
+    pub fn hartree_per_bohr2(value: f64) -> Self {
+        Self(value * HARTREE_TO_KCAL_PER_MOL / BOHR_TO_ANGSTROM.powi(2))
+    }
+
+    pub fn as_kcal_per_mol_per_angstrom2(&self) -> f64 {
+        self.0
+    }
+
+    pub fn as_kj_per_mol_per_nanometer2(&self) -> f64 {
+        self.0 * KCAL_TO_KJ * 100.0
+    }
+
+    pub fn as_hartree_per_bohr2(&self) -> f64 {
+        self.0 * BOHR_TO_ANGSTROM.powi(2) / HARTREE_TO_KCAL_PER_MOL
+    }
+}
+
+quantity! {
+    /// an angle bending force constant, stored in kcal/mol/rad²
+    AngleForceConstant, "kilocalorie / mole / radian ** 2"
+}
+
+impl AngleForceConstant {
+    pub fn kcal_per_mol_per_radian2(value: f64) -> Self {
+        Self(value)
+    }
+
+    pub fn kj_per_mol_per_radian2(value: f64) -> Self {
+        Self(value / KCAL_TO_KJ)
+    }
+
+    pub fn as_kcal_per_mol_per_radian2(&self) -> f64 {
+        self.0
+    }
+
+    pub fn as_kj_per_mol_per_radian2(&self) -> f64 {
+        self.0 * KCAL_TO_KJ
+    }
+}
+
+quantity! {
+    /// an angle, stored in degrees
+    Angle, "degree"
+}
+
+impl Angle {
+    pub fn degrees(value: f64) -> Self {
+        Self(value)
+    }
+
+    pub fn radians(value: f64) -> Self {
+        Self(value.to_degrees())
+    }
+
+    pub fn as_degrees(&self) -> f64 {
+        self.0
+    }
+
+    pub fn as_radians(&self) -> f64 {
+        self.0.to_radians()
+    }
+}
+
+quantity! {
+    /// a temperature, stored in kelvin
+    Temperature, "kelvin"
+}
+
+impl Temperature {
+    pub fn kelvin(value: f64) -> Self {
+        Self(value)
+    }
+
+    pub fn as_kelvin(&self) -> f64 {
+        self.0
+    }
+}
+
+quantity! {
+    /// a length of time, stored in picoseconds
+    Time, "picosecond"
+}
+
+impl Time {
+    pub fn femtoseconds(value: f64) -> Self {
+        Self(value / 1000.0)
+    }
+
+    pub fn picoseconds(value: f64) -> Self {
+        Self(value)
+    }
+
+    pub fn as_femtoseconds(&self) -> f64 {
+        self.0 * 1000.0
+    }
+
+    pub fn as_picoseconds(&self) -> f64 {
+        self.0
+    }
+}
+
+quantity! {
+    /// a frequency, like the friction coefficient of a Langevin integrator,
+    /// stored in ps⁻¹
+    Frequency, "1 / picosecond"
+}
+
+impl Frequency {
+    pub fn per_picosecond(value: f64) -> Self {
+        Self(value)
+    }
+
+    pub fn as_per_picosecond(&self) -> f64 {
+        self.0
+    }
+}
+
+quantity! {
+    /// a mass, stored in daltons
+    Mass, "dalton"
+}
+
+impl Mass {
+    pub fn daltons(value: f64) -> Self {
+        Self(value)
+    }
+
+    pub fn as_daltons(&self) -> f64 {
+        self.0
+    }
+}
+
+quantity! {
+    /// a concentration, like the ionic strength of a solvent, stored in
+    /// mol/L
+    Concentration, "molar"
+}
+
+impl Concentration {
+    pub fn molar(value: f64) -> Self {
+        Self(value)
+    }
+
+    pub fn millimolar(value: f64) -> Self {
+        Self(value / 1000.0)
+    }
+
+    pub fn as_molar(&self) -> f64 {
+        self.0
+    }
+
+    pub fn as_millimolar(&self) -> f64 {
+        self.0 * 1000.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-9 * a.abs().max(b.abs())
+    }
+
+    #[test]
+    fn constants() {
+        // reference values from CODATA 2018
+        assert!(close(1.0 / BOHR_TO_ANGSTROM, 1.889_726_124_6));
+        assert!(close(Energy::hartree(1.0).as_kj_per_mol(), 2_625.499_639));
+        assert!(close(Energy::kj_per_mol(4.184).as_kcal_per_mol(), 1.0));
+        assert!(close(Length::nanometers(1.0).as_bohr(), 18.897_261_246));
+        assert!(close(
+            Angle::radians(std::f64::consts::PI).as_degrees(),
+            180.0
+        ));
+        assert!(close(Time::femtoseconds(2.0).as_picoseconds(), 0.002));
+        assert!(close(
+            Force::kj_per_mol_per_nanometer(41.84)
+                .as_kcal_per_mol_per_angstrom(),
+            1.0
+        ));
+        assert!(close(
+            ForceConstant::kj_per_mol_per_nanometer2(418.4)
+                .as_kcal_per_mol_per_angstrom2(),
+            1.0
+        ));
+        assert!(close(
+            ForceConstant::hartree_per_bohr2(1.0)
+                .as_kcal_per_mol_per_angstrom2(),
+            HARTREE_TO_KCAL_PER_MOL / BOHR_TO_ANGSTROM / BOHR_TO_ANGSTROM
+        ));
+    }
+
+    #[test]
+    fn round_trips() {
+        let x = 1.234_567;
+        assert!(close(Length::bohr(x).as_bohr(), x));
+        assert!(close(Length::nanometers(x).as_nanometers(), x));
+        assert!(close(Energy::hartree(x).as_hartree(), x));
+        assert!(close(Energy::kj_per_mol(x).as_kj_per_mol(), x));
+        assert!(close(Force::hartree_per_bohr(x).as_hartree_per_bohr(), x));
+        assert!(close(
+            Force::kj_per_mol_per_nanometer(x).as_kj_per_mol_per_nanometer(),
+            x
+        ));
+        assert!(close(
+            ForceConstant::hartree_per_bohr2(x).as_hartree_per_bohr2(),
+            x
+        ));
+        assert!(close(
+            AngleForceConstant::kj_per_mol_per_radian2(x)
+                .as_kj_per_mol_per_radian2(),
+            x
+        ));
+        assert!(close(Angle::radians(x).as_radians(), x));
+        assert!(close(Time::femtoseconds(x).as_femtoseconds(), x));
+        assert!(close(Concentration::millimolar(x).as_millimolar(), x));
+    }
+
+    /// from_magnitude and magnitude work in [Quantity::UNIT], whatever
+    /// constructor produced the value
+    fn magnitude_round_trip<Q: Quantity + PartialEq + std::fmt::Debug>(q: Q) {
+        assert_eq!(Q::from_magnitude(q.magnitude()), q);
+    }
+
+    #[test]
+    fn magnitudes() {
+        assert_eq!(Length::from_magnitude(1.5), Length::angstroms(1.5));
+        assert_eq!(Energy::hartree(1.0).magnitude(), HARTREE_TO_KCAL_PER_MOL);
+        assert_eq!(Time::femtoseconds(1.0).magnitude(), 0.001);
+        assert_eq!(Length::UNIT, "angstrom");
+        magnitude_round_trip(Length::bohr(2.0));
+        magnitude_round_trip(Energy::kj_per_mol(2.0));
+        magnitude_round_trip(Force::hartree_per_bohr(2.0));
+        magnitude_round_trip(ForceConstant::hartree_per_bohr2(2.0));
+        magnitude_round_trip(AngleForceConstant::kj_per_mol_per_radian2(2.0));
+        magnitude_round_trip(Angle::radians(2.0));
+        magnitude_round_trip(Temperature::kelvin(2.0));
+        magnitude_round_trip(Time::femtoseconds(2.0));
+        magnitude_round_trip(Frequency::per_picosecond(2.0));
+        magnitude_round_trip(Mass::daltons(2.0));
+        magnitude_round_trip(Concentration::millimolar(2.0));
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = Energy::kcal_per_mol(3.0);
+        let b = Energy::kcal_per_mol(1.0);
+        assert_eq!(a + b, Energy::kcal_per_mol(4.0));
+        assert_eq!(a - b, Energy::kcal_per_mol(2.0));
+        assert_eq!(-a, Energy::kcal_per_mol(-3.0));
+        assert_eq!(a * 2.0, Energy::kcal_per_mol(6.0));
+        assert_eq!(a / 2.0, Energy::kcal_per_mol(1.5));
+        assert!(b < a);
+    }
+}
@@ -0,0 +1,199 @@
+//! normal mode analysis from finite-difference Hessians, for comparing MM and
+//! QM vibrational frequencies
+
+use nalgebra::{DMatrix, SymmetricEigen};
+
+use crate::forcefield::ForceField;
+use crate::molecule::Molecule;
+use crate::openmm::{Context, Integrator, Platform};
+use crate::units::{Force, ForceConstant, Length, Mass, Time};
+use crate::{Error, Result};
+
+/// converts the square root of an eigenvalue of a mass-weighted Hessian in
+/// kcal/mol/Å²/Da to a wavenumber in cm⁻¹
+const TO_WAVENUMBER: f64 = 108.591_358_611;
+
+/// the finite-difference step in Å used by [NormalModes::from_context]
+const HESSIAN_STEP: f64 = 1e-3;
+
+/// the RMS force tolerance in kJ/mol/nm used by [NormalModes::from_molecule]
+const MINIMIZE_TOLERANCE: f64 = 1e-4;
+
+pub struct NormalModes {
+    /// the frequency of each mode in cm⁻¹, in ascending order. imaginary
+    /// frequencies, from negative eigenvalues, are reported as negative
+    /// numbers. the six (or five, for linear molecules) translational and
+    /// rotational modes are included and should be close to zero at a
+    /// minimum
+    pub frequencies: Vec<f64>,
+
+    /// the normalized Cartesian displacement vector of each mode, flattened
+    /// like the positions, in the same order as `frequencies`. massless
+    /// particles like virtual sites are never displaced
+    pub modes: Vec<Vec<f64>>,
+}
+
+impl NormalModes {
+    /// perform a normal mode analysis on the flattened 3N×3N `hessian`, like
+    /// the output of [Context::hessian], for particles with `masses`.
+    /// particles with zero mass are excluded from the analysis. returns
+    /// [Error::AtomCountMismatch] if the size of `hessian` does not match the
+    /// number of `masses`
+    pub fn new(hessian: &[ForceConstant], masses: &[Mass]) -> Result<Self> {
+        let n = 3 * masses.len();
+        if hessian.len() != n * n {
+            return Err(Error::AtomCountMismatch {
+                expected: masses.len(),
+                got: (hessian.len() as f64).sqrt() as usize / 3,
+            });
+        }
+        // the coordinates of massive particles and the square root of their
+        // masses
+        let coords: Vec<(usize, f64)> = masses
+            .iter()
+            .enumerate()
+            .map(|(i, m)| (i, m.as_daltons()))
+            .filter(|&(_, m)| m > 0.0)
+            .flat_map(|(i, m)| (3 * i..3 * i + 3).map(move |c| (c, m.sqrt())))
+            .collect();
+        let m = coords.len();
+        let weighted = DMatrix::from_fn(m, m, |i, j| {
+            let (ci, si) = coords[i];
+            let (cj, sj) = coords[j];
+            hessian[ci * n + cj].as_kcal_per_mol_per_angstrom2() / (si * sj)
+        });
+        let eigen = SymmetricEigen::new(weighted);
+
+        let mut order: Vec<usize> = (0..m).collect();
+        order.sort_by(|&a, &b| {
+            eigen.eigenvalues[a].total_cmp(&eigen.eigenvalues[b])
+        });
+
+        let mut frequencies = Vec::with_capacity(m);
+        let mut modes = Vec::with_capacity(m);
+        for k in order {
+            let lambda = eigen.eigenvalues[k];
+            frequencies
+                .push(lambda.signum() * lambda.abs().sqrt() * TO_WAVENUMBER);
+            let mut mode = vec![0.0; n];
+            for (i, &(c, s)) in coords.iter().enumerate() {
+                mode[c] = eigen.eigenvectors[(i, k)] / s;
+            }
+            let norm = mode.iter().map(|x| x * x).sum::<f64>().sqrt();
+            mode.iter_mut().for_each(|x| *x /= norm);
+            modes.push(mode);
+        }
+        Ok(Self { frequencies, modes })
+    }
+
+    /// perform a normal mode analysis at the current positions of `context`,
+    /// which should already be minimized
+    pub fn from_context(context: &mut Context) -> Result<Self> {
+        let hessian = context.hessian(Length::angstroms(HESSIAN_STEP))?;
+        let masses = context.get_masses()?;
+        Self::new(&hessian, &masses)
+    }
+
+    /// parameterize `mol` with `forcefield`, minimize its conformer at index
+    /// `conformer`, and perform a normal mode analysis at the minimum. any
+    /// virtual sites added by `forcefield` are placed from the conformer's
+    /// atoms and, being massless, excluded from the modes
+    pub fn from_molecule(
+        mol: &Molecule,
+        forcefield: &ForceField,
+        conformer: usize,
+    ) -> Result<Self> {
+        let interchange = forcefield.create_interchange(mol.to_topology()?)?;
+        let mut context = Context::new(
+            interchange.to_openmm()?,
+            Integrator::Verlet(Time::femtoseconds(1.0)),
+            Platform::Reference,
+        )?;
+        context.set_atom_positions(mol.get_conformer(conformer)?)?;
+        // frequencies are only meaningful at a tightly converged minimum
+        context
+            .minimize(Force::kj_per_mol_per_nanometer(MINIMIZE_TOLERANCE), 0)?;
+        Self::from_context(&mut context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the Hessian of two particles joined by a spring with force constant
+    /// `k` along the x axis, padded with `extra` particles that feel no force
+    fn diatomic(k: f64, extra: usize) -> Vec<ForceConstant> {
+        let n = 3 * (2 + extra);
+        let mut hessian = vec![ForceConstant::default(); n * n];
+        let k = ForceConstant::kcal_per_mol_per_angstrom2(k);
+        hessian[0] = k;
+        hessian[3] = -k;
+        hessian[3 * n] = -k;
+        hessian[3 * n + 3] = k;
+        hessian
+    }
+
+    fn masses(masses: &[f64]) -> Vec<Mass> {
+        masses.iter().copied().map(Mass::daltons).collect()
+    }
+
+    #[test]
+    fn diatomic_frequency() {
+        let (k, m) = (500.0, 12.0);
+        let got = NormalModes::new(&diatomic(k, 0), &masses(&[m, m])).unwrap();
+        assert_eq!(got.frequencies.len(), 6);
+        for f in &got.frequencies[..5] {
+            assert!(f.abs() < 1e-3, "{f} is not a zero mode");
+        }
+        let want = (2.0 * k / m).sqrt() * TO_WAVENUMBER;
+        assert!((got.frequencies[5] - want).abs() < 1e-8);
+
+        // the stretch moves the two atoms apart along x
+        let stretch = &got.modes[5];
+        let half = 0.5f64.sqrt();
+        assert!((stretch[0].abs() - half).abs() < 1e-8);
+        assert!((stretch[0] + stretch[3]).abs() < 1e-8);
+    }
+
+    #[test]
+    fn massless_particles() {
+        let got =
+            NormalModes::new(&diatomic(500.0, 1), &masses(&[12.0, 12.0, 0.0]))
+                .unwrap();
+        assert_eq!(got.frequencies.len(), 6);
+        assert!(got.modes.iter().all(|m| m.len() == 9 && m[6..] == [0.0; 3]));
+    }
+
+    #[test]
+    fn mismatched_sizes() {
+        let got =
+            NormalModes::new(&diatomic(500.0, 0), &masses(&[12.0, 12.0, 1.0]));
+        assert!(matches!(
+            got,
+            Err(Error::AtomCountMismatch {
+                expected: 3,
+                got: 2
+            })
+        ));
+    }
+
+    #[test]
+    #[ignore = "requires openff-interchange and OpenMM"]
+    fn virtual_sites() {
+        use crate::molecule::Toolkit;
+
+        let ff = crate::molecule::tests::with_virtual_sites();
+        let mut mol = Molecule::from_smiles("O").unwrap();
+        mol.generate_conformers(1, None, Toolkit::RDKit).unwrap();
+        let got = NormalModes::from_molecule(&mol, &ff, 0).unwrap();
+        // three atoms, with the massless site excluded from the analysis
+        assert_eq!(got.frequencies.len(), 9);
+        for mode in &got.modes {
+            assert_eq!(mode.len(), 12);
+            let still = mode.chunks(3).filter(|c| c == &[0.0; 3]).count();
+            assert_eq!(still, 1, "only the virtual site should stay still");
+        }
+        assert!(got.frequencies[6..].iter().all(|&f| f > 0.0));
+    }
+}